@@ -0,0 +1,103 @@
+import { test, expect, type Page } from "@playwright/test";
+
+// three pairs of letters, laid out the same way on every run
+const GAME = "http://localhost:3000/classic/3?seed=1";
+
+const cardsOf = (page: Page) => page.locator(".card");
+const labelsOf = (page: Page) =>
+  cardsOf(page).evaluateAll((cards) =>
+    cards.map((card) => card.getAttribute("aria-label") ?? "")
+  );
+const nameOf = (label: string) => label.split(", ")[1];
+const isMatched = (label: string) => label.endsWith(", matched");
+
+test("cards are buttons labelled with their position", async ({ page }) => {
+  await page.goto(GAME);
+
+  const cards = cardsOf(page);
+  await expect(cards).toHaveCount(6);
+  for (let i = 0; i < 6; i++) {
+    await expect(cards.nth(i)).toHaveJSProperty("tagName", "BUTTON");
+    await expect(cards.nth(i)).toHaveAttribute(
+      "aria-label",
+      `Card ${i + 1} of 6, face down`
+    );
+    await expect(cards.nth(i)).toHaveAttribute("aria-disabled", "false");
+  }
+  await expect(page.getByRole("status")).toHaveAttribute("aria-live", "polite");
+});
+
+test("flipping a card reads out what's on it", async ({ page }) => {
+  await page.goto(GAME);
+
+  const card = cardsOf(page).first();
+  await card.click();
+  await expect(card).toHaveAttribute("aria-label", /^Card 1 of 6, [A-C]$/);
+});
+
+test("arrow keys move between cards and Enter flips them", async ({ page }) => {
+  await page.goto(GAME);
+
+  const cards = cardsOf(page);
+  await cards.first().focus();
+  await page.keyboard.press("ArrowRight");
+  await expect(cards.nth(1)).toBeFocused();
+  await page.keyboard.press("ArrowLeft");
+  await expect(cards.first()).toBeFocused();
+
+  await page.keyboard.press("Enter");
+  await expect(cards.first()).toHaveAttribute(
+    "aria-label",
+    /^Card 1 of 6, [A-C]$/
+  );
+});
+
+test("matches, mismatches and the win are announced", async ({ page }) => {
+  await page.goto(GAME);
+
+  const cards = cardsOf(page);
+  const status = page.getByRole("status");
+
+  // flip every card once to learn the board
+  const names: string[] = [];
+  for (let id = 0; id < 6; id++) {
+    const card = cards.nth(id);
+    if (!isMatched((await card.getAttribute("aria-label")) ?? "")) {
+      await card.click();
+      await expect(card).not.toHaveAttribute("aria-label", /face down$/);
+    }
+
+    const label = (await card.getAttribute("aria-label")) ?? "";
+    names[id] = nameOf(label);
+    if (label.endsWith(", no match")) {
+      await expect(status).toHaveText(`${names[id]} doesn't match`);
+    }
+  }
+
+  // then clear whatever is left, starting with a card still waiting for its partner
+  for (;;) {
+    const labels = await labelsOf(page);
+    const left = names
+      .map((_, id) => id)
+      .filter((id) => !isMatched(labels[id]));
+    if (left.length === 0) {
+      break;
+    }
+
+    const selected = left.find(
+      (id) => labels[id] === `Card ${id + 1} of 6, ${names[id]}`
+    );
+    const first = selected ?? left[0];
+    if (selected === undefined) {
+      await cards.nth(first).click();
+    }
+    const partner = left.find(
+      (id) => id !== first && names[id] === names[first]
+    )!;
+    await cards.nth(partner).click();
+    await expect(cards.nth(first)).toHaveAttribute("aria-label", /, matched$/);
+    await expect(status).toHaveText(/ matched$|^You win!$/);
+  }
+
+  await expect(status).toHaveText("You win!");
+});
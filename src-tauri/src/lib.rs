@@ -1,15 +1,56 @@
+use std::{fs, path::PathBuf};
+use tauri::Manager;
+
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+// Backs the frontend's storage module, one file per key in the app's data directory.
+fn item_path(app: &tauri::AppHandle, key: &str) -> Result<PathBuf, String> {
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("invalid key {key:?}"));
+    }
+
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| err.to_string())?
+        .join("storage");
+    Ok(dir.join(format!("{key}.json")))
+}
+
+#[tauri::command]
+fn load_item(app: tauri::AppHandle, key: String) -> Result<Option<String>, String> {
+    let path = item_path(&app, &key)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn store_item(app: tauri::AppHandle, key: String, value: String) -> Result<(), String> {
+    let path = item_path(&app, &key)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, value).map_err(|err| err.to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_window::init())
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .invoke_handler(tauri::generate_handler![greet, load_item, store_item])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,32 +1,34 @@
-use crate::error_template::{AppError, ErrorTemplate};
-use leptos::{
-    html::{Audio, Div},
-    *,
+use crate::{
+    audio::{fetch_packs, provide_audio, use_audio, Sound},
+    daily::{self, DailyResult},
+    deck::{self, fetch_deck, symbol_pairs, take_pairs, Face, Item, Pair, Pairs, CLASSIC_MODES},
+    engine::{
+        Board, CardState, Flip, FlipOutcome, ReplayError, Rules, Stats, Turns, MAX_PLAYERS,
+        MIN_FLIP_GAP,
+    },
+    error_template::{AppError, ErrorTemplate},
+    leaderboard::{finish_game, get_scores, start_game, SubmitScore, Ticket},
+    online::{self, BoardEvent, ClientMessage, RoomState, ServerMessage, Socket},
+    records::{self, load_personal_bests, GameKind, NewRecords},
+    replay::Replay,
+    settings::{provide_settings, use_settings, CardSize, Settings, Theme},
 };
+use leptos::{html::Div, *};
 use leptos_meta::*;
 use leptos_router::*;
 use log::debug;
 
-use std::{rc::Rc, time::Duration};
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
 
 #[component]
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    let settings = provide_settings();
 
-    let click_audio_ref = create_node_ref();
-    let error_click_audio_ref = create_node_ref();
-    let success_click_audio_ref = create_node_ref();
-
-    let audio_sources = move || {
-        Some(AudioSources {
-            click: click_audio_ref.get()?,
-            success_click: success_click_audio_ref.get()?,
-            error_click: error_click_audio_ref.get()?,
-        })
-    };
-
-    provide_context(audio_sources.into_signal());
+    provide_audio(settings);
 
     view! {
         // injects a stylesheet into the document <head>
@@ -36,58 +38,31 @@ pub fn App() -> impl IntoView {
         // sets the document title
         <Title text="Welcome to Leptos"/>
 
-        // sound effects
-        <audio
-            preload
-            node_ref=click_audio_ref
-            src="/sounds/default-click.wav"
-        />
-        <audio
-            preload
-            node_ref=error_click_audio_ref
-            src="/sounds/error-click.wav"
-        />
-        <audio
-            preload
-            node_ref=success_click_audio_ref
-            src="/sounds/success-click.wav"
-        />
+        <Body class=move || settings.with(Settings::body_class)/>
 
         // content for this welcome page
         <Router fallback=|| view! {<ErrorPage/>}>
             <main>
                 <Routes>
                     <Route path="" view=HomePage/>
-                    <ClassicRoutes/>
+                    <ClassicRoutes path="classic" group_size=2/>
+                    <ClassicRoutes path="triples" group_size=3/>
+                    <ClassicRoutes path="quads" group_size=4/>
+                    <ClassicRoutes path="clock" group_size=2/>
+                    <ClassicRoutes path="survival" group_size=2/>
+                    <Route path="deck/:id" view=DeckPage/>
+                    <Route path="daily" view=DailyPage/>
+                    <Route path="hotseat" view=HotSeatPage/>
+                    <Route path="online" view=OnlinePage/>
+                    <Route path="leaderboard" view=LeaderboardPage/>
+                    <Route path="replay" view=ReplayPage/>
+                    <Route path="settings" view=SettingsPage/>
                 </Routes>
             </main>
         </Router>
     }
 }
 
-struct AudioSources {
-    click: HtmlElement<Audio>,
-    success_click: HtmlElement<Audio>,
-    error_click: HtmlElement<Audio>,
-}
-
-trait PlayAudio {
-    fn play(&self, f: impl Fn(&AudioSources) -> &HtmlElement<Audio>);
-}
-
-impl PlayAudio for Signal<Option<AudioSources>> {
-    fn play(&self, f: impl Fn(&AudioSources) -> &HtmlElement<Audio>) {
-        self.with(|sources| {
-            if let Some(sources) = sources {
-                let audio = f(sources);
-                audio.set_volume(0.2);
-                audio.set_current_time(0.0);
-                let _ = audio.play();
-            }
-        });
-    }
-}
-
 #[component]
 fn ErrorPage() -> impl IntoView {
     let mut outside_errors = Errors::default();
@@ -102,25 +77,59 @@ fn HomePage() -> impl IntoView {
     view! {
         <div class="column gap">
             <A href="/classic" class="button">Classic</A>
+            <A href="/triples" class="button">Triples</A>
+            <A href="/quads" class="button">Quads</A>
+            <A href="/clock" class="button">Beat the Clock</A>
+            <A href="/survival" class="button">Survival</A>
+            <A href="/daily" class="button">Daily Challenge</A>
+            <A href="/hotseat" class="button">Hot Seat</A>
+            <A href="/online" class="button">Play Online</A>
+            <A href="/deck/animals" class="button">Animals</A>
+            <A href="/deck/spanish" class="button">Spanish</A>
+            <A href="/deck/shapes" class="button">Shapes</A>
+            <A href="/leaderboard" class="button">Leaderboard</A>
+            <A href="/replay" class="button">Replays</A>
+            <A href="/settings" class="button">Settings</A>
         </div>
     }
 }
 
 #[component(transparent)]
-fn ClassicRoutes() -> impl IntoView {
+fn ClassicRoutes(path: &'static str, group_size: usize) -> impl IntoView {
     let passthrough = || view! {<Outlet/>};
     view! {
-        <Route path="classic" view=passthrough>
-            <Route path="" view=ClassicPage/>
-            <Route path=":size" view=ClassicGamePage/>
+        <Route path=path view=passthrough>
+            <Route path="" view=move || view! {<ClassicPage mode=path group_size/>}/>
+            <Route path=":size" view=move || view! {<ClassicGamePage mode=path group_size/>}/>
         </Route>
     }
 }
 
 #[component]
-fn ClassicPage() -> impl IntoView {
+fn ClassicPage(mode: &'static str, group_size: usize) -> impl IntoView {
+    let bests = create_local_resource(|| (), |_| load_personal_bests());
     let links = (3..=20)
-        .map(|x| view! { <A href={x.to_string()} class="button">2x{x.to_string()}</A>})
+        .map(|x| {
+            let key = GameKind::new(mode, x).key();
+            let best = move || {
+                bests
+                    .with(|bests| bests.get(&key).copied())
+                    .flatten()
+                    .map(|best| {
+                        view! {
+                            <small class="best">
+                                {format_time(best.time)} " · " {best.moves} " moves"
+                            </small>
+                        }
+                    })
+            };
+            view! {
+                <A href={x.to_string()} class="button">
+                    {format!("{group_size}x{x}")}
+                    {best}
+                </A>
+            }
+        })
         .collect_view();
     view! {
         <div class="links rows gap">{links}</div>
@@ -132,200 +141,572 @@ struct ClassicGameParams {
     size: u8,
 }
 
+#[derive(Params, PartialEq, Eq, Clone, Copy)]
+struct GameQuery {
+    seed: Option<u64>,
+    players: Option<usize>,
+}
+
+/// Picks how many take turns, the board itself is a classic one.
 #[component]
-fn ClassicGamePage() -> impl IntoView {
-    let params = use_params::<ClassicGameParams>();
-    let game = move || {
-        params().map(|params| {
+fn HotSeatPage() -> impl IntoView {
+    let (players, set_players) = create_signal(2);
+    let player_buttons = (2..=MAX_PLAYERS)
+        .map(|n| {
             view! {
-                <ClassicGame number_of_pairs=params.size/>
+                <div class="button" class:selected=move || players() == n on:click=move |_| set_players(n)>
+                    {n} " players"
+                </div>
             }
         })
+        .collect_view();
+    let links = (3..=20)
+        .map(|x| {
+            view! {
+                <A href=move || format!("/classic/{x}?players={}", players()) class="button">
+                    {format!("2x{x}")}
+                </A>
+            }
+        })
+        .collect_view();
+    view! {
+        <Title text="Hot Seat"/>
+        <div class="links rows gap">{player_buttons}</div>
+        <div class="links rows gap">{links}</div>
+    }
+}
+
+#[component]
+fn ClassicGamePage(mode: &'static str, group_size: usize) -> impl IntoView {
+    let params = use_params::<ClassicGameParams>();
+    let query = use_query::<GameQuery>();
+    let game = move || {
+        let params = params().map_err(|_| AppError::NotFound)?;
+        let query = query().map_err(|_| AppError::NotFound)?;
+        let players = query.players.unwrap_or(1);
+        if !(1..=MAX_PLAYERS).contains(&players) {
+            return Err(AppError::NotFound);
+        }
+        Ok::<_, AppError>(view! {
+            <ClassicGame mode number_of_pairs=params.size group_size seed=query.seed players/>
+        })
     };
     view! {
-        <ErrorBoundary fallback=|_| view!{<ErrorPage/>}>
+        <ErrorBoundary fallback=|errors| view!{<ErrorTemplate errors/>}>
           {game}
         </ErrorBoundary>
     }
 }
 
 #[component]
-fn ClassicGame(number_of_pairs: u8) -> impl IntoView {
-    let number_of_pairs = number_of_pairs as usize;
-    let available_pairs = [
-        Pair {
-            matches: ["A".into(), "A".into()],
-        },
-        Pair {
-            matches: ["B".into(), "B".into()],
-        },
-        Pair {
-            matches: ["C".into(), "C".into()],
-        },
-        Pair {
-            matches: ["D".into(), "D".into()],
-        },
-        Pair {
-            matches: ["E".into(), "E".into()],
+fn ClassicGame(
+    mode: &'static str,
+    number_of_pairs: u8,
+    group_size: usize,
+    seed: Option<u64>,
+    players: usize,
+) -> impl IntoView {
+    take_pairs(symbol_pairs(group_size), number_of_pairs.into())
+        .map(|pairs| {
+            view! {
+                <Game
+                    options=pairs
+                    seed
+                    kind=Some(GameKind::new(mode, number_of_pairs.into()))
+                    players
+                />
+            }
+        })
+        .map_err(AppError::from)
+}
+
+#[derive(Params, PartialEq, Eq, Clone)]
+struct DeckParams {
+    id: String,
+}
+
+#[component]
+fn DeckPage() -> impl IntoView {
+    let params = use_params::<DeckParams>();
+    let query = use_query::<GameQuery>();
+    let deck = create_local_resource(
+        move || params().ok().map(|params| params.id),
+        |id| async move {
+            match id {
+                Some(id) => fetch_deck(&id).await.map_err(AppError::from),
+                None => Err(AppError::NotFound),
+            }
         },
-        Pair {
-            matches: ["F".into(), "F".into()],
+    );
+
+    let game = move || {
+        deck().map(|deck| {
+            deck.map(|deck| {
+                let seed = query().ok().and_then(|query| query.seed);
+                view! {
+                    <Title text=deck.name.to_string()/>
+                    <Game
+                        options=deck.pairs.clone()
+                        seed
+                        kind=Some(GameKind::new(format!("deck/{}", deck.id), deck.pairs.len()))
+                    />
+                }
+            })
+        })
+    };
+    view! {
+        <Suspense fallback=|| ()>
+            <ErrorBoundary fallback=|errors| view!{<ErrorTemplate errors/>}>
+                {game}
+            </ErrorBoundary>
+        </Suspense>
+    }
+}
+
+/// Today's board, only the attempt that flipped it first is scored.
+#[component]
+fn DailyPage() -> impl IntoView {
+    let today = create_local_resource(
+        || (),
+        |_| async {
+            let seed = daily::today();
+            let result = daily::load_result(seed).await;
+            (seed, result, daily::was_started(seed).await)
         },
-    ];
+    );
 
-    let mut pairs = Vec::new();
-    while pairs.len() < number_of_pairs.into() {
-        pairs.extend(
-            available_pairs
-                .iter()
-                .take(number_of_pairs - pairs.len())
-                .cloned(),
-        );
+    let page = move || {
+        today().map(|(seed, result, started)| match result {
+            Some(result) => Ok(view! { <DailyPlayed result/> }.into_view()),
+            None => daily::pairs().map_err(AppError::from).map(|pairs| {
+                // an attempt left unfinished has used up the day, the board can still be played
+                let kind = (!started).then(daily::kind);
+                view! {
+                    {started
+                        .then(|| {
+                            view! { <p>"Today's challenge was already started, this game isn't scored."</p> }
+                        })}
+                    <Game options=pairs seed=Some(seed) kind/>
+                }
+                .into_view()
+            }),
+        })
+    };
+    view! {
+        <Title text="Daily Challenge"/>
+        <Suspense fallback=|| ()>
+            <ErrorBoundary fallback=|errors| view!{<ErrorTemplate errors/>}>
+                {page}
+            </ErrorBoundary>
+        </Suspense>
     }
+}
 
+#[component]
+fn DailyPlayed(result: DailyResult) -> impl IntoView {
     view! {
-        <Game options=pairs.into()/>
+        <div class="column gap">
+            <h2>"Come back tomorrow!"</h2>
+            <p>"You've already played today's challenge."</p>
+            <textarea class="share" readonly>{share_text(&result)}</textarea>
+            <A class="button" href=leaderboard_href(&daily::kind(), Some(result.seed))>
+                "Leaderboard"
+            </A>
+            <A class="button" href="/">"Home"</A>
+        </div>
     }
 }
 
 #[component]
 fn GamePage() -> impl IntoView {
-    let options = [
-        Pair {
-            matches: ["A".into(), "A".into()],
-        },
-        Pair {
-            matches: ["B".into(), "B".into()],
-        },
-        Pair {
-            matches: ["C".into(), "C".into()],
-        },
-        Pair {
-            matches: ["D".into(), "D".into()],
-        },
-        Pair {
-            matches: ["E".into(), "E".into()],
-        },
-        Pair {
-            matches: ["F".into(), "F".into()],
-        },
-    ]
-    .into();
+    let options = symbol_pairs(2).take(6).collect();
 
     view! {
-        <Game options/>
+        <Game options seed=None kind=None/>
     }
 }
 
-type Pairs = Rc<[Pair]>;
-
+/// Personal bests are only kept when the game has a `kind` and a single player.
+///
+/// Those games are dealt and timed by the server, which it's only asked to do on the first flip,
+/// until then the board stays face down.
 #[component]
-fn Game(options: Pairs) -> impl IntoView {
-    let (pairs, set_pairs) = create_signal([].into());
-    let start = move || {
-        set_pairs(options.clone());
+fn Game(
+    options: Pairs,
+    seed: Option<u64>,
+    kind: Option<GameKind>,
+    #[prop(default = 1)] players: usize,
+) -> impl IntoView {
+    let scored_kind = kind.clone().filter(|_| players == 1);
+    let number_of_cards: usize = options.iter().map(Pair::group_size).sum();
+    let (deal, set_deal) = create_signal(None);
+    let requesting = store_value(false);
+
+    let start = {
+        let options = options.clone();
+        let scored = scored_kind.is_some();
+        move || {
+            requesting.set_value(false);
+            if scored {
+                set_deal(None);
+                return;
+            }
+            // without a seed every game gets a fresh layout
+            let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+            set_deal(Some((options.clone(), seed, None, None)));
+        }
     };
     let start_up = start.clone();
     create_effect(move |_| start_up());
 
+    let first_flip = move |id: usize| {
+        let Some(kind) = scored_kind.clone() else {
+            return;
+        };
+        if requesting.get_value() {
+            return;
+        }
+        requesting.set_value(true);
+        let options = options.clone();
+        spawn_local(async move {
+            match start_game(kind, seed).await {
+                Ok(ticket) => set_deal(Some((options, ticket.seed, Some(ticket), Some(id)))),
+                // still playable, only without a score for the leaderboard
+                Err(err) => {
+                    debug!("game not started on the server: {err}");
+                    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+                    set_deal(Some((options, seed, None, Some(id))));
+                }
+            }
+        });
+    };
+
+    let game_match = move || match deal() {
+        Some((pairs, seed, ticket, flipped)) => view! {
+            <GameMatch
+                pairs
+                seed
+                ticket
+                first_flip=flipped
+                kind=kind.clone()
+                players
+                restart=start.clone()
+            />
+        }
+        .into_view(),
+        None => view! { <FaceDownBoard number_of_cards flip=first_flip.clone()/> }.into_view(),
+    };
+
     view! {
-        <Show when=move || !pairs().is_empty() fallback=|| ()>
-            <GameMatch pairs=pairs() restart=start.clone()/>
-        </Show>
+        {game_match}
     }
 }
 
+/// A board nothing has been dealt on yet, flipping any card starts the game.
 #[component]
-fn GameMatch<Restart>(pairs: Pairs, restart: Restart) -> impl IntoView
+fn FaceDownBoard<FlipFn>(number_of_cards: usize, flip: FlipFn) -> impl IntoView
 where
-    Restart: Fn() + Clone + 'static,
+    FlipFn: Fn(usize) + Clone + 'static,
 {
-    let mut cards: Vec<_> = pairs
-        .into_iter()
-        .flat_map(|x| &x.matches)
-        .enumerate()
-        .map(|(id, item)| {
-            let (state, set_state) = create_signal(CardState::default());
-            CardData {
-                id,
-                item: item.clone(),
-                state,
-                set_state,
+    let cards = (0..number_of_cards)
+        .map(|id| {
+            let flip = flip.clone();
+            view! {
+                <button
+                    type="button"
+                    aria-label=card_label(id, number_of_cards, "", CardState::Unselected)
+                    aria-disabled="false"
+                    on:click=move |_| flip(id)
+                    class="card"
+                >
+                    <span class="back"></span>
+                </button>
             }
         })
-        .collect();
-    fastrand::shuffle(&mut cards);
-    let number_of_cards = cards.len();
-    let (cards_left, set_cards_left) = create_signal(cards.len());
+        .collect_view();
+
+    view! {
+        <div class="frame">
+            <div class="hud">
+                <span>"Moves: 0"</span>
+                <span>"Mismatches: 0"</span>
+                <span>"Time: " {format_time(Duration::ZERO)}</span>
+                <span>"Score: 0"</span>
+            </div>
+            <CardGrid number_of_cards>{cards}</CardGrid>
+            <Announcer message=Signal::derive(String::new)/>
+        </div>
+    }
+}
+
+#[component]
+fn GameMatch<Restart>(
+    pairs: Pairs,
+    seed: u64,
+    /// The server's hold on the game, scores can only be submitted with one.
+    ticket: Option<Ticket>,
+    /// Flipped straight away, the card that got the game dealt.
+    first_flip: Option<usize>,
+    kind: Option<GameKind>,
+    players: usize,
+    restart: Restart,
+) -> impl IntoView
+where
+    Restart: Fn() + Clone + 'static,
+{
+    let turns = create_rw_signal(Turns::new(players));
+    // scores only count when nobody else helped
+    let scored_kind = kind.clone().filter(|_| players == 1);
+    let board = create_rw_signal(deck::deal(&pairs, seed));
+    let number_of_cards = board.with_untracked(Board::len);
     let (win, set_win) = create_signal(false);
 
+    // every accepted flip, so the server can check the match was really played
+    let (flips, set_flips) = create_signal(Vec::<Flip>::new());
+
+    // the clock starts with the first flip and stops once the board is cleared
+    let (started_at, set_started_at) = create_signal(None::<f64>);
+    let (finished_at, set_finished_at) = create_signal(None::<f64>);
+    let (now, set_now) = create_signal(0.0);
     create_effect(move |_| {
-        if cards_left() == 0 {
-            set_timeout(move || set_win(true), Duration::from_secs(1));
+        let tick = move || {
+            if finished_at.get_untracked().is_none() {
+                set_now(js_sys::Date::now());
+            }
+        };
+        if let Ok(handle) = set_interval_with_handle(tick, Duration::from_millis(250)) {
+            on_cleanup(move || handle.clear());
         }
     });
+    let elapsed = move || {
+        started_at().map_or(Duration::ZERO, |start| {
+            let end = finished_at().unwrap_or(now());
+            Duration::from_millis((end - start).max(0.0) as u64)
+        })
+    };
+    let stats = create_memo(move |_| board.with(|board| board.stats(elapsed())));
 
-    let audio_sources = expect_context::<Signal<Option<AudioSources>>>();
-    let (_, set_selected) = create_signal(Vec::<CardData>::new());
-    let cards = cards
-        .into_iter()
-        .map(|card| {
-            let card_copy = card.clone();
-            let select = move || {
-                match (card_copy.state)() {
-                    CardState::Unselected | CardState::Failure => {}
-                    _ => {
-                        return;
-                    }
-                };
-                set_selected.update(|selected| {
-                    if selected.len() > 1 {
-                        for selected in selected.iter() {
-                            selected.set_state.update(|state| {
-                                *state = match *state {
-                                    CardState::Failure => CardState::Unselected,
-                                    CardState::Success => CardState::Hidden,
-                                    x => x,
-                                };
-                            })
-                        }
+    let rules = kind.as_ref().map(GameKind::rules).unwrap_or_default();
+    let time_left = move || rules.time_left(&stats());
+    let lives = rules.lives_left(&Stats::default()).unwrap_or_default();
+    let lives_left = move || rules.lives_left(&stats());
+    let (lost, set_lost) = create_signal(false);
+    let defeated = create_memo(move |_| rules.is_lost(&stats()));
 
-                        selected.clear();
-                    }
+    let loss_message = match rules {
+        Rules::Lives(_) => "💔 Out of lives! 💔",
+        _ => "⏰ Time's up! ⏰",
+    };
 
-                    if let Some(other) = selected.last() {
-                        if other.id != card_copy.id {
-                            let state = if other.item == card_copy.item {
-                                set_cards_left.update(|left| {
-                                    *left -= selected.len() + 1;
-                                });
-                                audio_sources.play(|a| &a.success_click);
-                                CardState::Success
-                            } else {
-                                audio_sources.play(|a| &a.error_click);
-                                CardState::Failure
-                            };
-
-                            other.set_state.set(state);
-                            card_copy.set_state.set(state);
-                            selected.push(card_copy.clone());
-                        }
-                    } else {
-                        card_copy.set_state.set(CardState::Selected);
-                        selected.push(card_copy.clone());
-                        audio_sources.play(|a| &a.click);
+    let audio = use_audio();
+    let (message, announce) = create_signal(String::new());
+    let (new_records, set_new_records) = create_signal(NewRecords::default());
+    let won = create_memo(move |_| board.with(Board::is_won));
+    let lose_audio = audio.clone();
+    create_effect(move |_| {
+        if defeated() && !won.get_untracked() {
+            set_finished_at(Some(js_sys::Date::now()));
+            lose_audio.play(Sound::Lose);
+            announce(loss_message.to_string());
+            set_timeout(move || set_lost(true), Duration::from_secs(1));
+        }
+    });
+    let record_kind = scored_kind.clone();
+    let finish_token = ticket.as_ref().map(|ticket| ticket.token.clone());
+    let win_audio = audio.clone();
+    create_effect(move |_| {
+        if won() {
+            set_finished_at(Some(js_sys::Date::now()));
+            // stops the server's clock before the player gets to typing a name
+            if let Some(token) = finish_token.clone() {
+                spawn_local(async move {
+                    if let Err(err) = finish_game(token).await {
+                        debug!("game not finished on the server: {err}");
                     }
                 });
-            };
+            }
+            win_audio.play(Sound::Win);
+            announce(if players == 1 {
+                "You win!".to_string()
+            } else {
+                headline(&turns.get_untracked(), &player_names(players))
+            });
+            if let Some(kind) = record_kind.clone() {
+                let stats = stats.get_untracked();
+                spawn_local(async move {
+                    if kind.mode == daily::MODE {
+                        daily::submit(seed, &stats).await;
+                    }
+                    set_new_records(records::submit(&kind, &stats).await);
+                });
+            }
+            set_timeout(move || set_win(true), Duration::from_secs(1));
+        }
+    });
 
-            let CardData { item, state, .. } = card;
+    let settle_later = use_settle(board);
+    let daily_kind = scored_kind
+        .as_ref()
+        .is_some_and(|kind| kind.mode == daily::MODE);
+    let flip = move |id: usize| {
+        // one reading of the clock for both, a flip let through right at the limit
+        // mustn't be stamped past it
+        let now = js_sys::Date::now();
+        // the tick might not have noticed the time running out yet
+        set_now(now);
+        if defeated.get_untracked() {
+            return;
+        }
+        // faster than anyone clicks, and the server would turn the whole game down for it
+        let too_soon = started_at.get_untracked().is_some_and(|started_at| {
+            flips.with_untracked(|flips| {
+                flips.last().is_some_and(|last| {
+                    now - started_at < (last.at_ms + MIN_FLIP_GAP.as_millis() as u64) as f64
+                })
+            })
+        });
+        if too_soon {
+            return;
+        }
+        let Some(outcome) = board.try_update(|board| board.flip(id)) else {
+            return;
+        };
+        if outcome == FlipOutcome::Ignored {
+            return;
+        }
+        let (name, clip) =
+            board.with_untracked(|board| (board.item(id).label(), board.item(id).audio.clone()));
+        audio.play_outcome(outcome);
+        if let Some(message) = announcement(outcome, &name) {
+            announce(message);
+        }
+        turns.update(|turns| turns.record(outcome));
+        if outcome != FlipOutcome::Selected {
+            settle_later();
+        }
+        let started_at = started_at.get_untracked().unwrap_or_else(|| {
+            set_started_at(Some(now));
+            if daily_kind {
+                spawn_local(daily::start(seed));
+            }
+            now
+        });
+        set_flips.update(|flips| {
+            flips.push(Flip {
+                card: id,
+                at_ms: (now - started_at) as u64,
+            })
+        });
+        if let Some(clip) = &clip {
+            audio.play_clip(clip);
+        }
+    };
+    let cards = (0..number_of_cards)
+        .map(|id| {
+            let (pair, face) =
+                board.with_untracked(|board| (board.pair(id), board.item(id).clone()));
+            let partners = partners(&pairs[pair], &face);
+            let state = create_memo(move |_| board.with(|board| board.state(id)));
+            let flip = flip.clone();
+            let select = move || flip(id);
 
             view! {
-                <Card item state select/>
+                <Card id number_of_cards face partners state select/>
             }
         })
         .collect_view();
+    if let Some(id) = first_flip {
+        flip(id);
+    }
 
+    view! {
+        <div class="frame">
+            <div class="hud">
+                <span>"Moves: " {move || stats().moves}</span>
+                <span>"Mismatches: " {move || stats().mismatches}</span>
+                <span>"Time: " {move || format_time(stats().elapsed)}</span>
+                {move || {
+                    time_left()
+                        .map(|left| {
+                            view! {
+                                <span class="time-left" class:urgent=left < Duration::from_secs(5)>
+                                    "Time left: " {format_time(left)}
+                                </span>
+                            }
+                        })
+                }}
+                {move || {
+                    lives_left()
+                        .map(|left| {
+                            view! {
+                                <span class="lives">
+                                    {"❤️".repeat(left as usize)}
+                                    {"🖤".repeat((lives - left) as usize)}
+                                </span>
+                            }
+                        })
+                }}
+                <span>"Score: " {move || stats().score}</span>
+            </div>
+            <Show when=move || players != 1 fallback=|| ()>
+                <Scoreboard names=player_names(players) turns/>
+            </Show>
+            <CardGrid number_of_cards>{cards}</CardGrid>
+            <Announcer message/>
+        </div>
+        <Show when=lost fallback=|| ()>
+            <LossScreen
+                message=loss_message
+                stats=stats.get_untracked()
+                restart=restart.clone()
+            />
+        </Show>
+        <Show when=win fallback=|| ()>
+            <WinScreen
+                kind=scored_kind.clone()
+                seed
+                ticket=ticket.clone()
+                stats=stats.get_untracked()
+                turns=turns.get_untracked()
+                flips=flips.get_untracked()
+                new_records
+                restart=restart.clone()
+            />
+        </Show>
+    }
+}
+
+/// Schedules settling the board after the delay in the settings, a newer selection gets the full
+/// delay again.
+fn use_settle(board: RwSignal<Board<Face>>) -> impl Fn() + Copy {
+    let settings = use_settings();
+    let pending = store_value(None::<TimeoutHandle>);
+    on_cleanup(move || {
+        pending.try_update_value(|pending| pending.take().map(TimeoutHandle::clear));
+    });
+
+    move || {
+        let Some(delay) = settings.with_untracked(|settings| settings.settle_delay) else {
+            return;
+        };
+        let settle = move || {
+            board.update(|board| {
+                board.settle();
+            })
+        };
+        if let Ok(handle) = set_timeout_with_handle(settle, delay) {
+            pending.update_value(|pending| {
+                if let Some(previous) = pending.replace(handle) {
+                    previous.clear();
+                }
+            });
+        }
+    }
+}
+
+/// Lays cards out in as many columns as lets them be the biggest.
+#[component]
+fn CardGrid(number_of_cards: usize, children: Children) -> impl IntoView {
     let board_ref = create_node_ref::<Div>();
     let (board_size, set_board_size) = create_signal(None);
 
@@ -344,15 +725,18 @@ where
     let board_aspect_ratio =
         move || board_size().map(|(width, height)| width as f32 / height as f32);
 
+    let settings = use_settings();
+    let card_size = move || settings.with(|settings| settings.card_size);
     let card_aspect_ratio = 1.4142;
-    let gap = 20;
+    let gap = move || card_size().gap();
     let columns = move || {
         board_aspect_ratio()
             .map(|x| num_columns(card_aspect_ratio, number_of_cards, x))
             .unwrap_or(1)
     };
 
-    let width = move || {
+    let fitted_width = move || {
+        let gap = gap();
         board_size()
             .map(|(width, height)| {
                 let card_width = (width as f32 - (gap * (columns() - 1)) as f32) / columns() as f32;
@@ -373,26 +757,75 @@ where
             })
             .unwrap_or(100.0)
     };
+    let width = move || {
+        let width = fitted_width();
+        card_size().max_width().map_or(width, |max| width.min(max))
+    };
+
+    // arrow keys move the focus between cards the way they're laid out
+    let move_focus = move |ev: ev::KeyboardEvent| {
+        let Some(board) = board_ref.get_untracked() else {
+            return;
+        };
+        let cards = board.children();
+        let focused = document().active_element();
+        let Some(current) = (0..number_of_cards).find(|&id| cards.item(id as u32) == focused)
+        else {
+            return;
+        };
+        let Some(next) = neighbour(current, &ev.key(), columns(), number_of_cards) else {
+            return;
+        };
+        ev.prevent_default();
+        if let Some(card) = cards
+            .item(next as u32)
+            .and_then(|card| card.dyn_into::<web_sys::HtmlElement>().ok())
+        {
+            let _ = card.focus();
+        }
+    };
 
     view! {
-        <div class="frame">
-            <div
-                node_ref=board_ref
-                class="board"
-                style:font-size=move || format!("{}px", width() / 2.0)
-                style=("--gap", format!("{}px",gap))
-                style=("--aspect-ratio", card_aspect_ratio)
-                style=("--width", move || format!("{}px", width()))
-            >
-                {cards}
-            </div>
+        <div
+            node_ref=board_ref
+            class="board"
+            on:keydown=move_focus
+            style=("--gap", move || format!("{}px", gap()))
+            style=("--aspect-ratio", card_aspect_ratio)
+            style=("--width", move || format!("{}px", width()))
+        >
+            {children()}
         </div>
-        <Show when=win fallback=|| ()>
-            <WinScreen restart=restart.clone()/>
-        </Show>
     }
 }
 
+// learning decks show the rest of the pair once it's found
+fn partners(pair: &Pair, face: &Face) -> Vec<Face> {
+    let mut partners: Vec<Face> = Vec::new();
+    for other in &pair.matches {
+        if other != face && !partners.contains(other) {
+            partners.push(other.clone());
+        }
+    }
+    partners
+}
+
+/// Plain text to paste anywhere, e.g. "Memory daily 2024-03-09: 24 moves in 1:05, score 835".
+fn share_text(result: &DailyResult) -> String {
+    format!(
+        "Memory daily {}: {} moves in {}, score {}",
+        daily::date(result.seed),
+        result.moves,
+        format_time(result.time),
+        result.score
+    )
+}
+
+fn format_time(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
 // aspect ratio is width/height.
 fn num_columns(card_aspect_ratio: f32, number_of_cards: usize, board_aspect_ratio: f32) -> usize {
     let mut best_aspect_ratio = aspect_ratio_of_layout(card_aspect_ratio, number_of_cards, 1);
@@ -409,6 +842,20 @@ fn num_columns(card_aspect_ratio: f32, number_of_cards: usize, board_aspect_rati
     return 1;
 }
 
+/// The card an arrow key moves to from `card`, with `columns` cards to a row.
+///
+/// Left and right go through the cards in reading order, on to the next row at the end of one.
+fn neighbour(card: usize, key: &str, columns: usize, number_of_cards: usize) -> Option<usize> {
+    let next = match key {
+        "ArrowLeft" => card.checked_sub(1)?,
+        "ArrowRight" => card + 1,
+        "ArrowUp" => card.checked_sub(columns)?,
+        "ArrowDown" => card + columns,
+        _ => return None,
+    };
+    (next < number_of_cards).then_some(next)
+}
+
 fn aspect_ratio_of_layout(card_aspect_ratio: f32, number_of_cards: usize, columns: usize) -> f32 {
     let rows = number_of_cards.div_ceil(columns);
 
@@ -418,23 +865,15 @@ fn aspect_ratio_of_layout(card_aspect_ratio: f32, number_of_cards: usize, column
     width / height
 }
 
-#[derive(Clone)]
-struct CardData {
-    id: usize,
-    item: Item,
-    state: ReadSignal<CardState>,
-    set_state: WriteSignal<CardState>,
-}
-
-type Item = Rc<str>;
-
-#[derive(Clone)]
-struct Pair {
-    matches: [Item; 2],
-}
-
 #[component]
-fn Card<StateFn, SelectFn>(item: Item, state: StateFn, mut select: SelectFn) -> impl IntoView
+fn Card<StateFn, SelectFn>(
+    id: usize,
+    number_of_cards: usize,
+    face: Face,
+    partners: Vec<Face>,
+    state: StateFn,
+    mut select: SelectFn,
+) -> impl IntoView
 where
     StateFn: Fn() -> CardState + Copy + 'static,
     SelectFn: FnMut() + 'static,
@@ -443,40 +882,1034 @@ where
     let success = move || state() == CardState::Success;
     let fail = move || state() == CardState::Failure;
     let show = move || state() != CardState::Hidden;
+    let has_partners = !partners.is_empty();
+    let name = face.label();
+    let label = move || card_label(id, number_of_cards, &name, state());
+    // a button gets focus, Enter and Space for free
     view! {
-        <div on:click=move |_| select() class="card" class:flipped=flipped class:success=success class:fail=fail>
+        <button
+            type="button"
+            aria-label=label
+            aria-disabled=move || (state() == CardState::Hidden).to_string()
+            on:click=move |_| select()
+            class="card"
+            class:flipped=flipped
+            class:success=success
+            class:fail=fail
+        >
             <Show when=show fallback=|| ()>
-                <div class="front">{item.to_string()}</div>
-                <div class="back"></div>
+                <span class="front">
+                    // colour alone doesn't tell everyone how a selection went
+                    <Show when=move || success() || fail() fallback=|| ()>
+                        <span class="result" aria-hidden="true">
+                            {move || if success() { "✓" } else { "✗" }}
+                        </span>
+                    </Show>
+                    <ItemView item=face.item.clone()/>
+                    <Show when=move || has_partners && success() fallback=|| ()>
+                        <span class="partner">
+                            {partners
+                                .iter()
+                                .map(|partner| view! { <ItemView item=partner.item.clone()/> })
+                                .collect_view()}
+                        </span>
+                    </Show>
+                </span>
+                <span class="back"></span>
             </Show>
-        </div>
+        </button>
+    }
+}
+
+/// What screen readers say about a card, e.g. "Card 3 of 12, face down".
+fn card_label(id: usize, number_of_cards: usize, name: &str, state: CardState) -> String {
+    let position = format!("Card {} of {number_of_cards}", id + 1);
+    match state {
+        CardState::Unselected => format!("{position}, face down"),
+        CardState::Selected => format!("{position}, {name}"),
+        CardState::Failure => format!("{position}, {name}, no match"),
+        CardState::Success | CardState::Hidden => format!("{position}, {name}, matched"),
+    }
+}
+
+/// What's announced after flipping the card called `name`, nothing for a selection that isn't
+/// complete yet.
+fn announcement(outcome: FlipOutcome, name: &str) -> Option<String> {
+    match outcome {
+        FlipOutcome::Match => Some(format!("{name} matched")),
+        FlipOutcome::Mismatch => Some(format!("{name} doesn't match")),
+        FlipOutcome::Selected | FlipOutcome::Ignored => None,
+    }
+}
+
+/// Read out by screen readers whenever `message` changes, invisible otherwise.
+#[component]
+fn Announcer(#[prop(into)] message: Signal<String>) -> impl IntoView {
+    view! {
+        <p class="visually-hidden" role="status" aria-live="polite">
+            {message}
+        </p>
     }
 }
 
-#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
-enum CardState {
-    #[default]
-    Unselected,
-    Selected,
-    Failure,
-    Success,
-    Hidden,
+#[component]
+fn ItemView(item: Item) -> impl IntoView {
+    match item {
+        Item::Text(text) => view! { <span class="text">{text.to_string()}</span> }.into_view(),
+        Item::Emoji(emoji) => view! { <span class="emoji">{emoji.to_string()}</span> }.into_view(),
+        Item::Image(src) => view! { <img class="image" src=src.to_string() alt=""/> }.into_view(),
+        Item::Svg(svg) => view! { <span class="svg" inner_html=svg.to_string()/> }.into_view(),
+    }
 }
 
 #[component]
-fn WinScreen<Restart>(restart: Restart) -> impl IntoView
+fn WinScreen<Restart>(
+    kind: Option<GameKind>,
+    seed: u64,
+    ticket: Option<Ticket>,
+    stats: Stats,
+    turns: Turns,
+    flips: Vec<Flip>,
+    new_records: ReadSignal<NewRecords>,
+    restart: Restart,
+) -> impl IntoView
 where
     Restart: Fn() + 'static,
 {
+    let headline = if turns.players() == 1 {
+        "🎉 You win! 🎉".to_string()
+    } else {
+        headline(&turns, &player_names(turns.players()))
+    };
+    let final_scores = (turns.players() > 1).then(|| {
+        view! { <FinalScores names=player_names(turns.players()) turns=turns.clone()/> }
+    });
+    let download = kind.clone().map(|kind| {
+        let replay = Replay {
+            kind,
+            seed,
+            flips: flips.clone(),
+        };
+        let href = format!(
+            "data:application/json;charset=utf-8,{}",
+            js_sys::encode_uri_component(&replay.to_json())
+        );
+        view! { <a class="button" href=href download="replay.json">"Download replay"</a> }
+    });
+    // only the first daily game is scored, so there's no playing it again
+    let daily = kind.as_ref().is_some_and(|kind| kind.mode == daily::MODE);
+    let share = daily.then(|| {
+        let summary = share_text(&DailyResult::new(seed, &stats));
+        view! { <textarea class="share" readonly>{summary}</textarea> }
+    });
     view! {
         <div class="shim">
             <div class="popup">
-                <h2>"🎉 You win! 🎉"</h2>
+                <h2>{headline}</h2>
+                {final_scores}
+                <dl class="stats">
+                    <dt>"Moves"</dt>
+                    <dd>{stats.moves}</dd>
+                    <dt>"Mismatches"</dt>
+                    <dd>{stats.mismatches}</dd>
+                    <dt>"Time"</dt>
+                    <dd>{format_time(stats.elapsed)}</dd>
+                    <dt>"Score"</dt>
+                    <dd>{stats.score}</dd>
+                </dl>
+                <Show when=move || new_records().any() fallback=|| ()>
+                    <p class="record">
+                        "🏆 "
+                        {move || match (new_records().time, new_records().moves) {
+                            (true, true) => "New best time and fewest moves!",
+                            (true, false) => "New best time!",
+                            _ => "Fewest moves yet!",
+                        }}
+                    </p>
+                </Show>
+                <p class="seed">"Seed: " {seed}</p>
+                {share}
                 <div class="column gap">
-                    <div class="button" on:click=move |_| restart()>"Play Again"</div>
+                    {kind
+                        .zip(ticket)
+                        .map(|(kind, ticket)| view! { <SubmitScoreForm kind ticket flips/> })}
+                    {download}
+                    {(!daily)
+                        .then(move || {
+                            view! { <div class="button" on:click=move |_| restart()>"Play Again"</div> }
+                        })}
                     <A class="button" href="/">"Home"</A>
                 </div>
             </div>
         </div>
     }
 }
+
+#[component]
+fn Scoreboard(
+    #[prop(into)] names: MaybeSignal<Vec<String>>,
+    #[prop(into)] turns: Signal<Turns>,
+) -> impl IntoView {
+    // players can still join an online room, so the seats are redrawn
+    let scores = move || {
+        names()
+            .into_iter()
+            .enumerate()
+            .map(|(player, name)| {
+                let current = move || turns.with(|turns| turns.current() == player);
+                let score = move || {
+                    turns.with(|turns| turns.scores().get(player).copied().unwrap_or_default())
+                };
+                view! {
+                    <span class="player" class:current=current>
+                        {name} ": " {score}
+                    </span>
+                }
+            })
+            .collect_view()
+    };
+    view! {
+        <div class="scoreboard">{scores}</div>
+    }
+}
+
+#[component]
+fn FinalScores(names: Vec<String>, turns: Turns) -> impl IntoView {
+    let scores = names
+        .into_iter()
+        .zip(turns.scores().to_vec())
+        .map(|(name, score)| {
+            view! {
+                <dt>{name}</dt>
+                <dd>{score}</dd>
+            }
+        })
+        .collect_view();
+    view! { <dl class="stats">{scores}</dl> }
+}
+
+fn player_names(players: usize) -> Vec<String> {
+    (1..=players)
+        .map(|player| format!("Player {player}"))
+        .collect()
+}
+
+/// Who won, or who tied for it.
+fn headline(turns: &Turns, names: &[String]) -> String {
+    match turns.leaders().as_slice() {
+        [winner] => format!("🎉 {} wins! 🎉", names[*winner]),
+        leaders => {
+            let leaders: Vec<_> = leaders
+                .iter()
+                .map(|&player| names[player].as_str())
+                .collect();
+            format!("🤝 It's a tie between {}! 🤝", leaders.join(" and "))
+        }
+    }
+}
+
+#[component]
+fn LossScreen<Restart>(message: &'static str, stats: Stats, restart: Restart) -> impl IntoView
+where
+    Restart: Fn() + 'static,
+{
+    view! {
+        <div class="shim">
+            <div class="popup lost">
+                <h2>{message}</h2>
+                <dl class="stats">
+                    <dt>"Moves"</dt>
+                    <dd>{stats.moves}</dd>
+                    <dt>"Mismatches"</dt>
+                    <dd>{stats.mismatches}</dd>
+                    <dt>"Time"</dt>
+                    <dd>{format_time(stats.elapsed)}</dd>
+                </dl>
+                <div class="column gap">
+                    <div class="button" on:click=move |_| restart()>"Try Again"</div>
+                    <A class="button" href="/">"Home"</A>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// The server replays `flips` itself and times the game by its own clock, so the score it keeps
+/// is the one it computed.
+#[component]
+fn SubmitScoreForm(kind: GameKind, ticket: Ticket, flips: Vec<Flip>) -> impl IntoView {
+    let submit = create_server_action::<SubmitScore>();
+    let (name, set_name) = create_signal(String::new());
+    let leaderboard = leaderboard_href(&kind, Some(ticket.seed));
+    let on_submit = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+        submit.dispatch(SubmitScore {
+            name: name.get_untracked(),
+            token: ticket.token.clone(),
+            flips: flips.clone(),
+        });
+    };
+    let submitted = move || matches!(submit.value()(), Some(Ok(_)));
+    let verified = move || {
+        submit.value()()
+            .and_then(Result::ok)
+            .map(|entry| view! { <p>"Score submitted: " {entry.score}</p> })
+    };
+    let error = move || {
+        submit.value()()
+            .and_then(Result::err)
+            .map(|err| view! { <p class="error">{err.to_string()}</p> })
+    };
+
+    view! {
+        <Show when=move || !submitted() fallback=verified>
+            <form class="submit-score column gap" on:submit=on_submit.clone()>
+                <input
+                    type="text"
+                    placeholder="Your name"
+                    maxlength="20"
+                    prop:value=move || name()
+                    on:input=move |ev| set_name(event_target_value(&ev))
+                />
+                <button class="button" type="submit" disabled=move || submit.pending()()>
+                    "Submit score"
+                </button>
+            </form>
+            {error}
+        </Show>
+        <A class="button" href=leaderboard>"Leaderboard"</A>
+    }
+}
+
+fn leaderboard_href(kind: &GameKind, seed: Option<u64>) -> String {
+    let mut href = format!("/leaderboard?mode={}&size={}", kind.mode, kind.size);
+    if let Some(seed) = seed {
+        href += &format!("&seed={seed}");
+    }
+    href
+}
+
+#[derive(Params, PartialEq, Eq, Clone, Default)]
+struct LeaderboardQuery {
+    mode: Option<String>,
+    size: Option<usize>,
+    seed: Option<u64>,
+}
+
+#[component]
+fn LeaderboardPage() -> impl IntoView {
+    let query = use_query::<LeaderboardQuery>();
+    let selection = move || {
+        let query = query().unwrap_or_default();
+        let kind = GameKind::new(
+            query.mode.unwrap_or_else(|| "classic".to_string()),
+            query.size.unwrap_or(8),
+        );
+        (kind, query.seed)
+    };
+    let scores = create_resource(selection, |(kind, seed)| get_scores(kind, seed));
+
+    let title = move || {
+        let (kind, seed) = selection();
+        match seed {
+            Some(seed) => format!("{} {} · seed {seed}", kind.mode, kind.size),
+            None => format!("{} {}", kind.mode, kind.size),
+        }
+    };
+    let mode_links = CLASSIC_MODES
+        .into_iter()
+        .map(|(mode, _)| {
+            let href = move || leaderboard_href(&GameKind::new(mode, selection().0.size), None);
+            view! { <A href=href class="button">{mode}</A> }
+        })
+        .collect_view();
+    let size_links = (3..=20)
+        .map(|size| {
+            let href = move || leaderboard_href(&GameKind::new(selection().0.mode, size), None);
+            view! { <A href=href class="button">{size}</A> }
+        })
+        .collect_view();
+    let rows = move || {
+        scores.get().map(|scores| match scores {
+            Ok(scores) if scores.is_empty() => view! { <p>"No scores yet."</p> }.into_view(),
+            Ok(scores) => view! {
+                <table class="leaderboard">
+                    <tr>
+                        <th>"#"</th>
+                        <th>"Name"</th>
+                        <th>"Score"</th>
+                        <th>"Moves"</th>
+                        <th>"Time"</th>
+                    </tr>
+                    {scores
+                        .into_iter()
+                        .enumerate()
+                        .map(|(rank, entry)| view! {
+                            <tr>
+                                <td>{rank + 1}</td>
+                                <td>{entry.name}</td>
+                                <td>{entry.score}</td>
+                                <td>{entry.moves}</td>
+                                <td>{format_time(Duration::from_millis(entry.time_ms))}</td>
+                            </tr>
+                        })
+                        .collect_view()}
+                </table>
+            }
+            .into_view(),
+            Err(err) => view! { <p class="error">{err.to_string()}</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <div class="links column gap">
+            <h1>"Leaderboard"</h1>
+            <h2>{title}</h2>
+            <div class="rows gap">{mode_links}</div>
+            <div class="rows gap">{size_links}</div>
+            <div class="popup">
+                <Transition fallback=|| ()>{rows}</Transition>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn ReplayPage() -> impl IntoView {
+    let (replay, set_replay) = create_signal(None);
+    let on_change = move |ev: ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        spawn_local(async move {
+            set_replay(Some(read_replay(file).await));
+        });
+    };
+
+    let viewer = move || {
+        replay().map(|replay| match replay {
+            Ok((replay, pairs)) => view! { <ReplayViewer replay pairs/> }.into_view(),
+            Err(err) => view! { <p class="error">{err.to_string()}</p> }.into_view(),
+        })
+    };
+    view! {
+        <Title text="Replay"/>
+        <div class="replay-file column gap">
+            <label class="button">
+                "Open replay"
+                <input type="file" accept="application/json,.json" on:change=on_change/>
+            </label>
+            <A href="/" class="button">"Home"</A>
+        </div>
+        {viewer}
+    }
+}
+
+async fn read_replay(file: web_sys::File) -> Result<(Replay, Pairs), AppError> {
+    let json = JsFuture::from(file.text())
+        .await
+        .ok()
+        .and_then(|json| json.as_string())
+        .ok_or_else(|| ReplayError::Parse("couldn't read the file".to_string()))?;
+    Replay::load(&json).await
+}
+
+/// Plays `replay` back on the board it was dealt, waiting between flips as long as the player did.
+#[component]
+fn ReplayViewer(replay: Replay, pairs: Pairs) -> impl IntoView {
+    let Replay { seed, flips, .. } = replay;
+    let board = create_rw_signal(deck::deal(&pairs, seed));
+    let number_of_cards = board.with_untracked(Board::len);
+    let total = flips.len();
+    let flips = store_value(flips);
+    let (position, set_position) = create_signal(0);
+    let (playing, set_playing) = create_signal(false);
+
+    let step = move || {
+        let Some(flip) = flips.with_value(|flips| flips.get(position.get_untracked()).copied())
+        else {
+            return;
+        };
+        board.update(|board| {
+            board.flip(flip.card);
+        });
+        set_position.update(|position| *position += 1);
+    };
+    let deal = store_value(pairs.clone());
+    let restart = move || {
+        set_playing(false);
+        board.set(deal.with_value(|pairs| deck::deal(pairs, seed)));
+        set_position(0);
+    };
+
+    create_effect(move |_| {
+        if !playing() {
+            return;
+        }
+        let position = position();
+        let delay = flips.with_value(|flips| {
+            let next = flips.get(position)?;
+            let previous = position
+                .checked_sub(1)
+                .map_or(0, |previous| flips[previous].at_ms);
+            Some(next.at_ms - previous)
+        });
+        let Some(delay) = delay else {
+            set_playing(false);
+            return;
+        };
+        if let Ok(handle) = set_timeout_with_handle(step, Duration::from_millis(delay)) {
+            on_cleanup(move || handle.clear());
+        }
+    });
+
+    let stats = create_memo(move |_| {
+        let elapsed = position()
+            .checked_sub(1)
+            .map_or(0, |last| flips.with_value(|flips| flips[last].at_ms));
+        board.with(|board| board.stats(Duration::from_millis(elapsed)))
+    });
+
+    let cards = (0..number_of_cards)
+        .map(|id| {
+            let (pair, face) =
+                board.with_untracked(|board| (board.pair(id), board.item(id).clone()));
+            let partners = partners(&pairs[pair], &face);
+            let state = create_memo(move |_| board.with(|board| board.state(id)));
+            view! {
+                <Card id number_of_cards face partners state select=|| ()/>
+            }
+        })
+        .collect_view();
+
+    let finished = move || position() >= total;
+    view! {
+        <div class="frame">
+            <div class="hud">
+                <span>"Flip: " {position} " / " {total}</span>
+                <span>"Moves: " {move || stats().moves}</span>
+                <span>"Mismatches: " {move || stats().mismatches}</span>
+                <span>"Time: " {move || format_time(stats().elapsed)}</span>
+                <span>"Score: " {move || stats().score}</span>
+            </div>
+            <div class="controls">
+                <button
+                    class="button"
+                    disabled=finished
+                    on:click=move |_| set_playing.update(|playing| *playing = !*playing)
+                >
+                    {move || if playing() { "Pause" } else { "Play" }}
+                </button>
+                <button
+                    class="button"
+                    disabled=finished
+                    on:click=move |_| {
+                        set_playing(false);
+                        step();
+                    }
+                >
+                    "Step"
+                </button>
+                <button class="button" on:click=move |_| restart()>"Restart"</button>
+            </div>
+            <CardGrid number_of_cards>{cards}</CardGrid>
+        </div>
+    }
+}
+
+#[derive(Params, PartialEq, Eq, Clone)]
+struct OnlineQuery {
+    watch: Option<String>,
+}
+
+/// Opens, joins or watches a room, the match itself is played through the server.
+///
+/// `?watch=CODE` goes straight to watching, handy for a screen that only shows the match.
+#[component]
+fn OnlinePage() -> impl IntoView {
+    let query = use_query::<OnlineQuery>();
+    let (name, set_name) = create_signal(String::new());
+    let (size, set_size) = create_signal(8);
+    let (code, set_code) = create_signal(String::new());
+    let (hello, set_hello) = create_signal(
+        query
+            .get_untracked()
+            .ok()
+            .and_then(|query| query.watch)
+            .map(|code| ClientMessage::Watch { code }),
+    );
+
+    let create = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+        set_hello(Some(ClientMessage::Create {
+            name: name.get_untracked(),
+            size: size.get_untracked(),
+        }));
+    };
+    let join = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+        set_hello(Some(ClientMessage::Join {
+            name: name.get_untracked(),
+            code: code.get_untracked(),
+        }));
+    };
+
+    let watch = move |_| {
+        set_hello(Some(ClientMessage::Watch {
+            code: code.get_untracked(),
+        }));
+    };
+
+    let page = move || match hello() {
+        Some(hello) => view! {
+            <OnlineRoom hello leave=move || set_hello(None)/>
+        }
+        .into_view(),
+        None => view! {
+            <div class="lobby column gap">
+                <input
+                    type="text"
+                    placeholder="Your name"
+                    maxlength="20"
+                    prop:value=name
+                    on:input=move |ev| set_name(event_target_value(&ev))
+                />
+                <form class="column gap" on:submit=create>
+                    <label>
+                        "Pairs "
+                        <input
+                            type="number"
+                            min=*online::SIZES.start()
+                            max=*online::SIZES.end()
+                            prop:value=size
+                            on:input=move |ev| {
+                                if let Ok(size) = event_target_value(&ev).parse() {
+                                    set_size(size);
+                                }
+                            }
+                        />
+                    </label>
+                    <button class="button" type="submit">"Create room"</button>
+                </form>
+                <form class="column gap" on:submit=join>
+                    <input
+                        type="text"
+                        placeholder="Room code"
+                        maxlength="4"
+                        prop:value=code
+                        on:input=move |ev| set_code(event_target_value(&ev))
+                    />
+                    <button class="button" type="submit">"Join room"</button>
+                    <div class="button" on:click=watch>"Watch room"</div>
+                </form>
+                <A href="/" class="button">"Home"</A>
+            </div>
+        }
+        .into_view(),
+    };
+    view! {
+        <Title text="Play Online"/>
+        {page}
+    }
+}
+
+#[component]
+fn OnlineRoom<Leave>(hello: ClientMessage, leave: Leave) -> impl IntoView
+where
+    Leave: Fn() + Copy + 'static,
+{
+    let (room, set_room) = create_signal(None::<RoomState>);
+    let (seat, set_seat) = create_signal(None);
+    let (error, set_error) = create_signal(None);
+
+    let socket = store_value(None::<Socket>);
+    create_effect(move |_| {
+        let on_message = move |message| match message {
+            ServerMessage::Joined { player, room } => {
+                set_seat(Some(player));
+                set_room(Some(room));
+            }
+            ServerMessage::Watching { room } | ServerMessage::Update { room } => {
+                set_room(Some(room))
+            }
+            ServerMessage::Error { message } => set_error(Some(message)),
+        };
+        match Socket::connect(hello.clone(), on_message) {
+            Ok(connected) => socket.set_value(Some(connected)),
+            Err(err) => set_error(Some(format!("Couldn't connect: {err:?}"))),
+        }
+    });
+    on_cleanup(move || {
+        socket.try_update_value(Option::take);
+    });
+
+    // the board only needs dealing once, everything after that comes in as flips
+    let deal =
+        create_memo(move |_| room.with(|room| room.as_ref().map(|room| (room.size, room.seed))));
+    let game = move || {
+        deal().map(|(size, seed)| {
+            take_pairs(symbol_pairs(2), size)
+                .map(|pairs| view! { <OnlineMatch pairs seed room seat socket/> })
+                .map_err(AppError::from)
+        })
+    };
+    let error = move || {
+        error().map(|error| {
+            view! {
+                <p class="error">{error}</p>
+            }
+        })
+    };
+    view! {
+        {error}
+        <ErrorBoundary fallback=|errors| view!{<ErrorTemplate errors/>}>
+            {game}
+        </ErrorBoundary>
+        <Show when=move || deal().is_none() fallback=|| ()>
+            <div class="column gap">
+                <p>"Connecting…"</p>
+                <div class="button" on:click=move |_| leave()>"Back"</div>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+fn OnlineMatch(
+    pairs: Pairs,
+    seed: u64,
+    room: ReadSignal<Option<RoomState>>,
+    seat: ReadSignal<Option<usize>>,
+    socket: StoredValue<Option<Socket>>,
+) -> impl IntoView {
+    let board = create_rw_signal(deck::deal(&pairs, seed));
+    let number_of_cards = board.with_untracked(Board::len);
+    let code = room.with_untracked(|room| room.as_ref().map(|room| room.code.clone()));
+    let names = create_memo(move |_| {
+        room.with(|room| {
+            room.as_ref().map(|room| {
+                room.players
+                    .iter()
+                    .enumerate()
+                    .map(|(player, name)| {
+                        if room.turns.has_left(player) {
+                            format!("{name} (left)")
+                        } else {
+                            name.clone()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .unwrap_or_default()
+    });
+    let turns = create_memo(move |_| {
+        room.with(|room| room.as_ref().map(|room| room.turns.clone()))
+            .unwrap_or_else(|| Turns::new(1))
+    });
+    let waiting = move || room.with(|room| room.as_ref().map_or(true, RoomState::is_waiting));
+    let abandoned =
+        create_memo(move |_| room.with(|room| room.as_ref().is_some_and(RoomState::is_abandoned)));
+    let spectating = move || seat().is_none();
+    let my_turn = move || !waiting() && !abandoned() && seat() == Some(turns.with(Turns::current));
+
+    // catch up on everything the server did to the board since the last update
+    let audio = use_audio();
+    let win_audio = audio.clone();
+    let (message, announce) = create_signal(String::new());
+    let applied = store_value(0);
+    let settle_later = use_settle(board);
+    create_effect(move |_| {
+        room.with(|room| {
+            let Some(room) = room else {
+                return;
+            };
+            let new_events = &room.events[applied.get_value().min(room.events.len())..];
+            for &event in new_events {
+                let outcome = board.try_update(|board| event.apply(board)).flatten();
+                let BoardEvent::Flip { card } = event else {
+                    continue;
+                };
+                // someone tuning in halfway shouldn't hear the whole match at once
+                if let (Some(outcome), [_]) = (outcome, new_events) {
+                    audio.play_outcome(outcome);
+                    let name = board.with_untracked(|board| board.item(card).label());
+                    if let Some(message) = announcement(outcome, &name) {
+                        announce(message);
+                    }
+                }
+                if matches!(outcome, Some(FlipOutcome::Match | FlipOutcome::Mismatch)) {
+                    settle_later();
+                }
+            }
+            applied.set_value(room.events.len());
+        });
+    });
+    let won = create_memo(move |_| board.with(Board::is_won));
+    create_effect(move |_| {
+        if won() {
+            win_audio.play(Sound::Win);
+            announce(headline(&turns.get_untracked(), &names.get_untracked()));
+        }
+    });
+
+    let cards = (0..number_of_cards)
+        .map(|id| {
+            let (pair, face) =
+                board.with_untracked(|board| (board.pair(id), board.item(id).clone()));
+            let partners = partners(&pairs[pair], &face);
+            let state = create_memo(move |_| board.with(|board| board.state(id)));
+            let select = move || {
+                if my_turn() {
+                    socket.with_value(|socket| {
+                        if let Some(socket) = socket {
+                            socket.send(&ClientMessage::Flip { card: id });
+                        }
+                    });
+                }
+            };
+            view! {
+                <Card id number_of_cards face partners state select/>
+            }
+        })
+        .collect_view();
+
+    let status = move || {
+        if waiting() {
+            "Waiting for players…".to_string()
+        } else if abandoned() {
+            "Everyone else left".to_string()
+        } else if my_turn() {
+            "Your turn!".to_string()
+        } else {
+            let current = turns.with(Turns::current);
+            names.with(|names| format!("{}'s turn", names[current]))
+        }
+    };
+    view! {
+        <div class="frame">
+            <div class="hud">
+                <span>"Room: " <strong class="room-code">{code}</strong></span>
+                <span>{status}</span>
+                <Show when=spectating fallback=|| ()>
+                    <span class="spectating">"Spectating"</span>
+                </Show>
+            </div>
+            <Scoreboard names turns/>
+            <CardGrid number_of_cards>{cards}</CardGrid>
+            <Announcer message/>
+        </div>
+        <Show when=move || won() || abandoned() fallback=|| ()>
+            <div class="shim">
+                <div class="popup">
+                    <h2>
+                        {if won.get_untracked() {
+                            headline(&turns.get_untracked(), &names.get_untracked())
+                        } else {
+                            "🚪 Everyone else left 🚪".to_string()
+                        }}
+                    </h2>
+                    <FinalScores names=names.get_untracked() turns=turns.get_untracked()/>
+                    <A class="button" href="/">"Home"</A>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+fn SettingsPage() -> impl IntoView {
+    let settings = use_settings();
+    // delays in milliseconds, 0 stands for waiting on the next flip
+    let delays = [0, 500, 1000, 1200, 1500, 2000, 3000];
+    let delay_options = delays
+        .into_iter()
+        .map(|ms| {
+            let label = match ms {
+                0 => "Until the next flip".to_string(),
+                ms => format!("{} s", ms as f64 / 1000.0),
+            };
+            let selected = move || {
+                settings.with(|settings| {
+                    settings
+                        .settle_delay
+                        .map_or(0, |delay| delay.as_millis() as u64)
+                        == ms
+                })
+            };
+            view! { <option value=ms selected=selected>{label}</option> }
+        })
+        .collect_view();
+    let set_delay = move |ev| {
+        let ms: u64 = event_target_value(&ev).parse().unwrap_or_default();
+        settings.update(|settings| {
+            settings.settle_delay = (ms > 0).then(|| Duration::from_millis(ms));
+        });
+    };
+
+    let size_options = CardSize::ALL
+        .into_iter()
+        .map(|size| {
+            let selected = move || settings.with(|settings| settings.card_size == size);
+            view! { <option value=size.id() selected=selected>{size.name()}</option> }
+        })
+        .collect_view();
+    let set_size = move |ev| {
+        let id = event_target_value(&ev);
+        if let Some(size) = CardSize::ALL.into_iter().find(|size| size.id() == id) {
+            settings.update(|settings| settings.card_size = size);
+        }
+    };
+
+    let theme_options = Theme::ALL
+        .into_iter()
+        .map(|theme| {
+            let selected = move || settings.with(|settings| settings.theme == theme);
+            view! { <option value=theme.id() selected=selected>{theme.name()}</option> }
+        })
+        .collect_view();
+    let set_theme = move |ev| {
+        let id = event_target_value(&ev);
+        if let Some(theme) = Theme::ALL.into_iter().find(|theme| theme.id() == id) {
+            settings.update(|settings| settings.theme = theme);
+        }
+    };
+
+    let packs = create_local_resource(|| (), |_| fetch_packs());
+    let pack_options = move || {
+        let packs = match packs() {
+            Some(Ok(packs)) => packs,
+            Some(Err(err)) => {
+                log::warn!("couldn't load the sound packs: {err}");
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        packs
+            .into_iter()
+            .map(|pack| {
+                let id = pack.id.clone();
+                let selected = move || settings.with(|settings| settings.sound_pack == id);
+                view! { <option value=pack.id selected=selected>{pack.name}</option> }
+            })
+            .collect_view()
+    };
+    let set_pack = move |ev| {
+        let id = event_target_value(&ev);
+        settings.update(|settings| settings.sound_pack = id);
+    };
+
+    let sound_sliders = Sound::ALL
+        .into_iter()
+        .map(|sound| {
+            view! {
+                <label>
+                    {sound.name()}
+                    <input
+                        type="range"
+                        min="0"
+                        max="1"
+                        step="0.05"
+                        prop:value=move || settings.with(|settings| settings.sound_volume(sound))
+                        on:input=move |ev| {
+                            if let Ok(volume) = event_target_value(&ev).parse() {
+                                settings.update(|settings| {
+                                    settings.sound_volumes.insert(sound, volume);
+                                });
+                            }
+                        }
+                    />
+                </label>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <Title text="Settings"/>
+        <form class="settings column gap" on:submit=|ev| ev.prevent_default()>
+            <label>
+                "Volume"
+                <input
+                    type="range"
+                    min="0"
+                    max="1"
+                    step="0.05"
+                    prop:value=move || settings.with(|settings| settings.volume)
+                    on:input=move |ev| {
+                        if let Ok(volume) = event_target_value(&ev).parse() {
+                            settings.update(|settings| settings.volume = volume);
+                        }
+                    }
+                />
+            </label>
+            <label>
+                "Mute"
+                <input
+                    type="checkbox"
+                    prop:checked=move || settings.with(|settings| settings.muted)
+                    on:change=move |ev| {
+                        settings.update(|settings| settings.muted = event_target_checked(&ev))
+                    }
+                />
+            </label>
+            <label>
+                "Sounds"
+                <select on:change=set_pack>{pack_options}</select>
+            </label>
+            {sound_sliders}
+            <label>
+                "Turn cards back"
+                <select on:change=set_delay>{delay_options}</select>
+            </label>
+            <label>
+                "Card size"
+                <select on:change=set_size>{size_options}</select>
+            </label>
+            <label>
+                "Theme"
+                <select on:change=set_theme>{theme_options}</select>
+            </label>
+            <label>
+                "Reduced motion"
+                <input
+                    type="checkbox"
+                    prop:checked=move || settings.with(|settings| settings.reduced_motion)
+                    on:change=move |ev| {
+                        settings
+                            .update(|settings| {
+                                settings.reduced_motion = event_target_checked(&ev)
+                            })
+                    }
+                />
+            </label>
+            <A href="/" class="button">"Home"</A>
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 columns, the last row short:
+    // 0 1 2
+    // 3 4 5
+    // 6
+    #[test]
+    fn arrow_keys_stay_on_the_board() {
+        assert_eq!(neighbour(4, "ArrowUp", 3, 7), Some(1));
+        assert_eq!(neighbour(1, "ArrowUp", 3, 7), None);
+        assert_eq!(neighbour(3, "ArrowDown", 3, 7), Some(6));
+        assert_eq!(neighbour(4, "ArrowDown", 3, 7), None);
+        assert_eq!(neighbour(0, "ArrowLeft", 3, 7), None);
+        assert_eq!(neighbour(6, "ArrowRight", 3, 7), None);
+        assert_eq!(neighbour(4, "Enter", 3, 7), None);
+    }
+
+    #[test]
+    fn left_and_right_wrap_between_rows() {
+        assert_eq!(neighbour(2, "ArrowRight", 3, 7), Some(3));
+        assert_eq!(neighbour(3, "ArrowLeft", 3, 7), Some(2));
+    }
+}
@@ -0,0 +1,377 @@
+//! Sound effects through Web Audio, decoded once and mixed so quick clicks can overlap.
+//!
+//! The sounds come from a pack in `public/sounds/<pack>/`, described by its `pack.json`, and the
+//! packs to pick from are listed in `public/sounds/packs.json`.
+//!
+//! Nothing can play during SSR, every call is a no-op until the browser has set up the mixer.
+
+use crate::{engine::FlipOutcome, settings::Settings};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{
+    AudioBuffer, AudioBufferSourceNode, AudioContext, AudioNode, AudioScheduledSourceNode, GainNode,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sound {
+    Click,
+    Success,
+    Error,
+    Win,
+    Lose,
+    /// Loops in the background for as long as the pack is in use.
+    Music,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 6] = [
+        Sound::Click,
+        Sound::Success,
+        Sound::Error,
+        Sound::Win,
+        Sound::Lose,
+        Sound::Music,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Click => "Flips",
+            Sound::Success => "Matches",
+            Sound::Error => "Mismatches",
+            Sound::Win => "Wins",
+            Sound::Lose => "Losses",
+            Sound::Music => "Music",
+        }
+    }
+
+    pub fn for_outcome(outcome: FlipOutcome) -> Option<Self> {
+        match outcome {
+            FlipOutcome::Selected => Some(Sound::Click),
+            FlipOutcome::Match => Some(Sound::Success),
+            FlipOutcome::Mismatch => Some(Sound::Error),
+            FlipOutcome::Ignored => None,
+        }
+    }
+}
+
+pub const DEFAULT_PACK: &str = "default";
+
+/// An entry of `packs.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct PackInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// A `pack.json`, every file is in the pack's folder and any of them can be left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct SoundPack {
+    pub name: String,
+    pub click: Option<String>,
+    pub success: Option<String>,
+    pub error: Option<String>,
+    pub win: Option<String>,
+    pub lose: Option<String>,
+    pub music: Option<String>,
+    #[serde(skip)]
+    id: String,
+}
+
+impl SoundPack {
+    fn src(&self, sound: Sound) -> Option<String> {
+        let file = match sound {
+            Sound::Click => &self.click,
+            Sound::Success => &self.success,
+            Sound::Error => &self.error,
+            Sound::Win => &self.win,
+            Sound::Lose => &self.lose,
+            Sound::Music => &self.music,
+        };
+        let file = file.as_ref()?;
+        // packs are added and removed as a whole, one reaching into another would break with it
+        if file.split('/').any(|part| part == "..") {
+            log::warn!("{file} is outside the {} sound pack", self.id);
+            return None;
+        }
+        Some(format!("/sounds/{}/{file}", self.id))
+    }
+}
+
+pub async fn fetch_packs() -> Result<Vec<PackInfo>, String> {
+    fetch_json("/sounds/packs.json").await
+}
+
+pub async fn fetch_pack(id: &str) -> Result<SoundPack, String> {
+    if !crate::deck::is_valid_id(id) {
+        return Err(format!("{id} isn't a valid sound pack"));
+    }
+    let mut pack: SoundPack = fetch_json(&format!("/sounds/{id}/pack.json")).await?;
+    pack.id = id.to_string();
+    Ok(pack)
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
+    let response = gloo_net::http::Request::get(url)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !response.ok() {
+        return Err(format!("{url}: {}", response.status_text()));
+    }
+    response.json().await.map_err(|err| err.to_string())
+}
+
+/// Handle to the mixer, cheap to clone.
+#[derive(Clone, Default)]
+pub struct Audio(Rc<RefCell<Option<Mixer>>>);
+
+struct Mixer {
+    context: AudioContext,
+    master: GainNode,
+    effects: HashMap<Sound, GainNode>,
+    /// Decoded clips by where they were loaded from, deck clips included.
+    buffers: HashMap<String, AudioBuffer>,
+    /// Clips still on their way, everyone after the first to ask waits on the same load.
+    loading: HashMap<String, js_sys::Promise>,
+    pack: SoundPack,
+    music: Option<AudioBufferSourceNode>,
+}
+
+impl Mixer {
+    fn new() -> Result<Self, JsValue> {
+        let context = AudioContext::new()?;
+        let master = context.create_gain()?;
+        master.connect_with_audio_node(&context.destination())?;
+        let mut effects = HashMap::new();
+        for sound in Sound::ALL {
+            let gain = context.create_gain()?;
+            gain.connect_with_audio_node(&master)?;
+            effects.insert(sound, gain);
+        }
+
+        Ok(Self {
+            context,
+            master,
+            effects,
+            buffers: HashMap::new(),
+            loading: HashMap::new(),
+            pack: SoundPack::default(),
+            music: None,
+        })
+    }
+
+    // every play gets its own source node, that's what lets them overlap
+    fn start(
+        &self,
+        buffer: &AudioBuffer,
+        sound: Option<Sound>,
+    ) -> Result<AudioBufferSourceNode, JsValue> {
+        let source = self.context.create_buffer_source()?;
+        source.set_buffer(Some(buffer));
+        let output: &AudioNode = match sound.and_then(|sound| self.effects.get(&sound)) {
+            Some(gain) => gain,
+            None => &self.master,
+        };
+        source.connect_with_audio_node(output)?;
+        source.set_loop(sound == Some(Sound::Music));
+        source.start()?;
+        Ok(source)
+    }
+
+    fn stop_music(&mut self) {
+        if let Some(music) = self.music.take() {
+            let _ = AudioScheduledSourceNode::stop(&music);
+        }
+    }
+}
+
+impl Audio {
+    /// Sounds the current pack leaves out stay silent.
+    pub fn play(&self, sound: Sound) {
+        let src = self
+            .0
+            .borrow()
+            .as_ref()
+            .and_then(|mixer| mixer.pack.src(sound));
+        if let Some(src) = src {
+            self.play_src(&src, Some(sound));
+        }
+    }
+
+    pub fn play_outcome(&self, outcome: FlipOutcome) {
+        if let Some(sound) = Sound::for_outcome(outcome) {
+            self.play(sound);
+        }
+    }
+
+    /// A deck's own sound for a card, fetched the first time it's played.
+    pub fn play_clip(&self, src: &str) {
+        self.play_src(src, None);
+    }
+
+    fn play_src(&self, src: &str, sound: Option<Sound>) {
+        let buffer = {
+            let mixer = self.0.borrow();
+            let Some(mixer) = mixer.as_ref() else {
+                return;
+            };
+            // browsers keep the context suspended until the page has been interacted with
+            let _ = mixer.context.resume();
+            mixer.buffers.get(src).cloned()
+        };
+
+        let audio = self.clone();
+        let src = src.to_string();
+        spawn_local(async move {
+            let buffer = match buffer {
+                Some(buffer) => buffer,
+                None => match audio.load(&src).await {
+                    Ok(buffer) => buffer,
+                    Err(err) => {
+                        log::warn!("couldn't load {src}: {err:?}");
+                        return;
+                    }
+                },
+            };
+            if let Some(mixer) = audio.0.borrow().as_ref() {
+                if let Err(err) = mixer.start(&buffer, sound) {
+                    log::warn!("couldn't play {src}: {err:?}");
+                }
+            }
+        });
+    }
+
+    /// Swaps every sound for the ones in `pack`, the music starts over.
+    async fn use_pack(&self, pack: SoundPack) {
+        match self.0.borrow_mut().as_mut() {
+            Some(mixer) => {
+                mixer.stop_music();
+                mixer.pack = pack.clone();
+            }
+            None => return,
+        }
+
+        for sound in Sound::ALL {
+            let Some(src) = pack.src(sound) else {
+                continue;
+            };
+            let buffer = match self.load(&src).await {
+                Ok(buffer) => buffer,
+                Err(err) => {
+                    log::warn!("couldn't load {src}: {err:?}");
+                    continue;
+                }
+            };
+            if sound != Sound::Music {
+                continue;
+            }
+
+            let mut mixer = self.0.borrow_mut();
+            let Some(mixer) = mixer.as_mut() else {
+                return;
+            };
+            // another pack might have been picked while this one was loading
+            if mixer.pack != pack {
+                return;
+            }
+            match mixer.start(&buffer, Some(Sound::Music)) {
+                Ok(music) => mixer.music = Some(music),
+                Err(err) => log::warn!("couldn't play {src}: {err:?}"),
+            }
+        }
+    }
+
+    async fn load(&self, src: &str) -> Result<AudioBuffer, JsValue> {
+        let loading = {
+            let mut mixer = self.0.borrow_mut();
+            let Some(mixer) = mixer.as_mut() else {
+                return Err("no mixer".into());
+            };
+            if let Some(buffer) = mixer.buffers.get(src) {
+                return Ok(buffer.clone());
+            }
+            let context = mixer.context.clone();
+            let url = src.to_string();
+            mixer
+                .loading
+                .entry(src.to_string())
+                .or_insert_with(|| future_to_promise(decode(context, url)))
+                .clone()
+        };
+        let buffer = JsFuture::from(loading)
+            .await
+            .and_then(JsValue::dyn_into::<AudioBuffer>);
+
+        // a failed load is tried again the next time the clip is asked for
+        if let Some(mixer) = self.0.borrow_mut().as_mut() {
+            mixer.loading.remove(src);
+            if let Ok(buffer) = &buffer {
+                mixer.buffers.insert(src.to_string(), buffer.clone());
+            }
+        }
+        buffer
+    }
+
+    fn set_volumes(&self, settings: &Settings) {
+        if let Some(mixer) = self.0.borrow().as_ref() {
+            mixer
+                .master
+                .gain()
+                .set_value(settings.effective_volume() as f32);
+            for (sound, gain) in &mixer.effects {
+                gain.gain().set_value(settings.sound_volume(*sound) as f32);
+            }
+        }
+    }
+}
+
+// resolves to the `AudioBuffer`, a promise so that any number of plays can wait on it
+async fn decode(context: AudioContext, src: String) -> Result<JsValue, JsValue> {
+    let bytes = gloo_net::http::Request::get(&src)
+        .send()
+        .await
+        .map_err(|err| JsValue::from(err.to_string()))?
+        .binary()
+        .await
+        .map_err(|err| JsValue::from(err.to_string()))?;
+    let data = js_sys::Uint8Array::from(&bytes[..]).buffer();
+    JsFuture::from(context.decode_audio_data(&data)?).await
+}
+
+/// Sets the mixer up once running in the browser and keeps its volumes in line with `settings`.
+pub fn provide_audio(settings: RwSignal<Settings>) -> Audio {
+    let audio = Audio::default();
+    provide_context(audio.clone());
+
+    let setup = audio.clone();
+    create_effect(move |_| match Mixer::new() {
+        Ok(mixer) => *setup.0.borrow_mut() = Some(mixer),
+        Err(err) => log::warn!("no Web Audio, playing without sound: {err:?}"),
+    });
+
+    let pack_id = create_memo(move |_| settings.with(|settings| settings.sound_pack.clone()));
+    let packing = audio.clone();
+    create_effect(move |_| {
+        let id = pack_id();
+        let audio = packing.clone();
+        spawn_local(async move {
+            match fetch_pack(&id).await {
+                Ok(pack) => audio.use_pack(pack).await,
+                Err(err) => log::warn!("couldn't load sound pack {id}: {err}"),
+            }
+        });
+    });
+
+    let mixing = audio.clone();
+    create_effect(move |_| settings.with(|settings| mixing.set_volumes(settings)));
+
+    audio
+}
+
+pub fn use_audio() -> Audio {
+    expect_context()
+}
@@ -0,0 +1,125 @@
+//! One board a day, the same for everyone.
+
+use crate::{
+    deck::{symbol_pairs, take_pairs, DeckError, Pairs},
+    engine::Stats,
+    records::GameKind,
+    storage,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const MODE: &str = "daily";
+
+/// Pairs on the daily board.
+pub const SIZE: usize = 10;
+
+const STORAGE_KEY: &str = "daily-result";
+const STARTED_KEY: &str = "daily-started";
+
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+pub fn kind() -> GameKind {
+    GameKind::new(MODE, SIZE)
+}
+
+pub fn pairs() -> Result<Pairs, DeckError> {
+    take_pairs(symbol_pairs(2), SIZE)
+}
+
+/// Today's seed, the UTC date written as `yyyymmdd` so every timezone plays the same board.
+pub fn today() -> u64 {
+    seed_on((js_sys::Date::now() / DAY_MS).floor() as i64)
+}
+
+/// The seed of the UTC day `time` falls on, for the server, which has no `js_sys`.
+#[cfg(feature = "ssr")]
+pub fn seed_at(time: std::time::SystemTime) -> u64 {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    seed_on((since_epoch.as_millis() as f64 / DAY_MS).floor() as i64)
+}
+
+/// The seed for the UTC date `days` after 1970-01-01.
+fn seed_on(days: i64) -> u64 {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months counted from March, so the leap day comes last
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year * 10000 + month * 100 + day) as u64
+}
+
+/// e.g. `2024-03-09`.
+pub fn date(seed: u64) -> String {
+    format!("{}-{:02}-{:02}", seed / 10000, seed / 100 % 100, seed % 100)
+}
+
+/// How the scored attempt at a day's board went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub seed: u64,
+    pub moves: u32,
+    pub time: Duration,
+    pub score: u32,
+}
+
+impl DailyResult {
+    pub fn new(seed: u64, stats: &Stats) -> Self {
+        Self {
+            seed,
+            moves: stats.moves,
+            time: stats.elapsed,
+            score: stats.score,
+        }
+    }
+}
+
+pub async fn load_result(seed: u64) -> Option<DailyResult> {
+    storage::load_json::<DailyResult>(STORAGE_KEY)
+        .await
+        .filter(|result| result.seed == seed)
+}
+
+/// Remembers that `seed` was flipped on, leaving a bad start and reloading doesn't give another go.
+pub async fn start(seed: u64) {
+    storage::store_json(STARTED_KEY, &seed).await;
+}
+
+pub async fn was_started(seed: u64) -> bool {
+    storage::load_json::<u64>(STARTED_KEY).await == Some(seed)
+}
+
+/// Keeps the first game finished on `seed`, later ones don't count.
+pub async fn submit(seed: u64, stats: &Stats) -> DailyResult {
+    if let Some(result) = load_result(seed).await {
+        return result;
+    }
+
+    let result = DailyResult::new(seed, stats);
+    storage::store_json(STORAGE_KEY, &result).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_utc_dates() {
+        assert_eq!(seed_on(0), 19700101);
+        assert_eq!(seed_on(-1), 19691231);
+        assert_eq!(seed_on(11_017), 20000301);
+        assert_eq!(seed_on(19_782), 20240229);
+        assert_eq!(seed_on(19_791), 20240309);
+        assert_eq!(date(seed_on(19_791)), "2024-03-09");
+    }
+}
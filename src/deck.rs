@@ -0,0 +1,543 @@
+use crate::engine::Board;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::rc::Rc;
+use thiserror::Error;
+
+/// What is shown on the front of a card.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Text(Rc<str>),
+    Emoji(Rc<str>),
+    /// Url of an image, usually one of the assets in `public/`.
+    Image(Rc<str>),
+    /// Inline svg markup.
+    Svg(Rc<str>),
+}
+
+impl Item {
+    /// Words for screen readers, pictures go by their file name.
+    pub fn describe(&self) -> String {
+        match self {
+            Item::Text(text) | Item::Emoji(text) => text.to_string(),
+            Item::Image(src) => {
+                let file = src.rsplit('/').next().unwrap_or_default();
+                let name = file.split('.').next().unwrap_or_default();
+                name.replace(['-', '_'], " ")
+            }
+            Item::Svg(_) => "picture".to_string(),
+        }
+    }
+}
+
+/// A card's item and the clip played when it's flipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Face {
+    pub item: Item,
+    pub audio: Option<Rc<str>>,
+    /// Read out instead of describing the item.
+    pub label: Option<Rc<str>>,
+}
+
+impl Face {
+    pub fn label(&self) -> String {
+        match &self.label {
+            Some(label) => label.to_string(),
+            None => self.item.describe(),
+        }
+    }
+}
+
+impl From<Item> for Face {
+    fn from(item: Item) -> Self {
+        Self {
+            item,
+            audio: None,
+            label: None,
+        }
+    }
+}
+
+/// The cards that match each other, one face per card.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pair {
+    pub matches: Vec<Face>,
+}
+
+impl Pair {
+    /// A group of `group_size` identical cards.
+    pub fn identical(face: Face, group_size: usize) -> Self {
+        Self {
+            matches: vec![face; group_size],
+        }
+    }
+
+    pub fn group_size(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn shares_item(&self, other: &Pair) -> bool {
+        self.matches.iter().any(|face| {
+            other
+                .matches
+                .iter()
+                .any(|other_face| other_face.item == face.item)
+        })
+    }
+}
+
+pub type Pairs = Rc<[Pair]>;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum DeckError {
+    #[error("Deck only has {available} unique pairs but {requested} are needed")]
+    NotEnoughPairs { requested: usize, available: usize },
+    #[error("\"{0}\" is not a valid deck id")]
+    InvalidId(String),
+    #[error("Couldn't load deck: {0}")]
+    Fetch(String),
+    #[error("Couldn't read deck: {0}")]
+    Parse(String),
+    #[error("Deck has no name")]
+    MissingName,
+    #[error("Deck has no pairs")]
+    NoPairs,
+    #[error("Card in pair {pair} has nothing to show")]
+    EmptyCard { pair: usize },
+    #[error("Card in pair {pair} can only show one of text, emoji, image or svg")]
+    AmbiguousCard { pair: usize },
+    #[error("Pair {pair} shares a card with another pair")]
+    DuplicatePair { pair: usize },
+    #[error("Cards must match in groups of at least 2, not {0}")]
+    InvalidGroupSize(usize),
+    #[error("Pair {pair} has {found} cards but the deck matches groups of {expected}")]
+    WrongGroupSize {
+        pair: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// The symbol modes and how many cards make a match in each.
+pub const CLASSIC_MODES: [(&str, usize); 5] = [
+    ("classic", 2),
+    ("triples", 3),
+    ("quads", 4),
+    ("clock", 2),
+    ("survival", 2),
+];
+
+pub fn classic_group_size(mode: &str) -> Option<usize> {
+    CLASSIC_MODES
+        .iter()
+        .find(|(name, _)| *name == mode)
+        .map(|(_, group_size)| *group_size)
+}
+
+/// Lays out every card of `pairs`, the same pairs and seed always give the same board.
+pub fn deal(pairs: &[Pair], seed: u64) -> Board<Face> {
+    let group_size = pairs.first().map_or(2, Pair::group_size);
+    let faces = pairs
+        .iter()
+        .enumerate()
+        .flat_map(|(pair, x)| x.matches.iter().map(move |face| (pair, face.clone())));
+    Board::deal(faces, group_size, seed)
+}
+
+/// Endless sequence of distinct symbols: A, B, ..., Z, AA, AB, ...
+pub fn symbols() -> impl Iterator<Item = Rc<str>> {
+    (0usize..).map(|mut index| {
+        let mut symbol = String::new();
+        loop {
+            symbol.insert(0, char::from(b'A' + (index % 26) as u8));
+            index /= 26;
+            if index == 0 {
+                break Rc::from(symbol);
+            }
+            index -= 1;
+        }
+    })
+}
+
+pub fn symbol_pairs(group_size: usize) -> impl Iterator<Item = Pair> {
+    symbols().map(move |symbol| Pair::identical(Item::Text(symbol).into(), group_size))
+}
+
+/// Takes the first `number_of_pairs` pairs from `pairs`, skipping any pair
+/// that shares an item with one already taken so every pair can only match itself.
+pub fn take_pairs(
+    pairs: impl IntoIterator<Item = Pair>,
+    number_of_pairs: usize,
+) -> Result<Pairs, DeckError> {
+    let mut taken: Vec<Pair> = Vec::with_capacity(number_of_pairs);
+    for pair in pairs {
+        if taken.len() == number_of_pairs {
+            break;
+        }
+        if !taken.iter().any(|other| other.shares_item(&pair)) {
+            taken.push(pair);
+        }
+    }
+
+    if taken.len() < number_of_pairs {
+        return Err(DeckError::NotEnoughPairs {
+            requested: number_of_pairs,
+            available: taken.len(),
+        });
+    }
+
+    Ok(taken.into())
+}
+
+/// A deck as stored in `public/decks/<id>.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeckFile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// How many cards make a match, 2 unless stated otherwise.
+    #[serde(default = "default_group_size")]
+    pub group_size: usize,
+    pub pairs: Vec<PairFile>,
+}
+
+fn default_group_size() -> usize {
+    2
+}
+
+/// Either a single card that is dealt `group_size` times, or every card of the group.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum PairFile {
+    Twin(CardFile),
+    Cards(Vec<CardFile>),
+}
+
+impl<'de> Deserialize<'de> for PairFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // untagged would only say that neither variant fits, not which field is wrong
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let pair = if value.is_array() {
+            serde_json::from_value(value).map(PairFile::Cards)
+        } else {
+            serde_json::from_value(value).map(PairFile::Twin)
+        };
+        pair.map_err(de::Error::custom)
+    }
+}
+
+/// A card shows exactly one of `text`, `emoji`, `image` or `svg`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CardFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// Path relative to `public/`, or a full url.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<String>,
+    /// What screen readers call the card, worth giving to anything that isn't text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl CardFile {
+    fn into_face(self, pair: usize) -> Result<Face, DeckError> {
+        let mut items = [
+            self.text.map(|text| Item::Text(text.into())),
+            self.emoji.map(|emoji| Item::Emoji(emoji.into())),
+            self.image
+                .map(|image| Item::Image(asset_url(&image).into())),
+            self.svg.map(|svg| Item::Svg(svg.into())),
+        ]
+        .into_iter()
+        .flatten();
+
+        let item = items.next().ok_or(DeckError::EmptyCard { pair })?;
+        if items.next().is_some() {
+            return Err(DeckError::AmbiguousCard { pair });
+        }
+
+        Ok(Face {
+            item,
+            audio: self.audio.map(|audio| asset_url(&audio).into()),
+            label: self.label.map(Rc::from),
+        })
+    }
+}
+
+/// Paths in deck files are relative to the site root, which serves `public/`.
+fn asset_url(path: &str) -> String {
+    if path.starts_with('/') || path.contains(':') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Deck {
+    pub id: Rc<str>,
+    pub name: Rc<str>,
+    pub description: Rc<str>,
+    pub pairs: Pairs,
+}
+
+impl Deck {
+    pub fn from_json(id: &str, json: &str) -> Result<Self, DeckError> {
+        let file = serde_json::from_str(json).map_err(|err| DeckError::Parse(err.to_string()))?;
+        Self::from_file(id, file)
+    }
+
+    pub fn from_file(id: &str, file: DeckFile) -> Result<Self, DeckError> {
+        if file.name.trim().is_empty() {
+            return Err(DeckError::MissingName);
+        }
+        if file.pairs.is_empty() {
+            return Err(DeckError::NoPairs);
+        }
+        let group_size = file.group_size;
+        if group_size < 2 {
+            return Err(DeckError::InvalidGroupSize(group_size));
+        }
+
+        let mut pairs: Vec<Pair> = Vec::with_capacity(file.pairs.len());
+        for (index, pair) in file.pairs.into_iter().enumerate() {
+            let pair = match pair {
+                PairFile::Twin(card) => Pair::identical(card.into_face(index)?, group_size),
+                PairFile::Cards(cards) if cards.len() != group_size => {
+                    return Err(DeckError::WrongGroupSize {
+                        pair: index,
+                        expected: group_size,
+                        found: cards.len(),
+                    });
+                }
+                PairFile::Cards(cards) => Pair {
+                    matches: cards
+                        .into_iter()
+                        .map(|card| card.into_face(index))
+                        .collect::<Result<_, _>>()?,
+                },
+            };
+            if pairs.iter().any(|other| other.shares_item(&pair)) {
+                return Err(DeckError::DuplicatePair { pair: index });
+            }
+            pairs.push(pair);
+        }
+
+        Ok(Self {
+            id: id.into(),
+            name: file.name.into(),
+            description: file.description.into(),
+            pairs: pairs.into(),
+        })
+    }
+}
+
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Loads `public/decks/<id>.json` from wherever the site is served.
+pub async fn fetch_deck(id: &str) -> Result<Deck, DeckError> {
+    if !is_valid_id(id) {
+        return Err(DeckError::InvalidId(id.to_string()));
+    }
+
+    let response = gloo_net::http::Request::get(&format!("/decks/{id}.json"))
+        .send()
+        .await
+        .map_err(|err| DeckError::Fetch(err.to_string()))?;
+    if !response.ok() {
+        return Err(DeckError::Fetch(format!(
+            "{} {}",
+            response.status(),
+            response.status_text()
+        )));
+    }
+    let json = response
+        .text()
+        .await
+        .map_err(|err| DeckError::Fetch(err.to_string()))?;
+
+    Deck::from_json(id, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn names(pairs: &[Pair]) -> Vec<String> {
+        pairs
+            .iter()
+            .map(|pair| pair.matches[0].item.describe())
+            .collect()
+    }
+
+    #[test]
+    fn symbols_roll_over_like_spreadsheet_columns() {
+        let symbols: Vec<_> = symbols().take(703).collect();
+        let at = |index: usize| symbols[index].to_string();
+        assert_eq!(at(0), "A");
+        assert_eq!(at(25), "Z");
+        assert_eq!(at(26), "AA");
+        assert_eq!(at(27), "AB");
+        assert_eq!(at(51), "AZ");
+        assert_eq!(at(52), "BA");
+        assert_eq!(at(701), "ZZ");
+        assert_eq!(at(702), "AAA");
+    }
+
+    #[test]
+    fn every_size_gets_distinct_symbols() {
+        for size in (3..=20).chain([100, 800]) {
+            let pairs = take_pairs(symbol_pairs(2), size).unwrap();
+            let names = names(&pairs);
+            assert_eq!(names.len(), size);
+            assert_eq!(names.iter().collect::<HashSet<_>>().len(), size);
+        }
+    }
+
+    #[test]
+    fn groups_have_the_requested_size() {
+        let pairs = take_pairs(symbol_pairs(4), 3).unwrap();
+        assert!(pairs.iter().all(|pair| pair.group_size() == 4));
+    }
+
+    #[test]
+    fn pairs_sharing_an_item_are_skipped() {
+        let a = || Pair::identical(Item::Text("A".into()).into(), 2);
+        let b = || Pair::identical(Item::Text("B".into()).into(), 2);
+        let pairs = take_pairs([a(), a(), b()], 2).unwrap();
+        assert_eq!(names(&pairs), ["A", "B"]);
+
+        assert_eq!(
+            take_pairs([a(), a(), b()], 3).unwrap_err(),
+            DeckError::NotEnoughPairs {
+                requested: 3,
+                available: 2
+            }
+        );
+    }
+
+    fn deck(json: &str) -> Result<Deck, DeckError> {
+        Deck::from_json("test", json)
+    }
+
+    #[test]
+    fn shipped_decks_load() {
+        for id in ["animals", "shapes", "spanish"] {
+            let path = format!("{}/public/decks/{id}.json", env!("CARGO_MANIFEST_DIR"));
+            let json = std::fs::read_to_string(path).unwrap();
+            assert!(deck(&json).is_ok(), "{id}");
+        }
+    }
+
+    #[test]
+    fn deck_files_load_twins_and_groups() {
+        let deck = deck(
+            r#"{"name": "Mixed", "pairs": [
+                {"emoji": "🐶"},
+                [{"text": "dog"}, {"image": "images/dog.png", "label": "a dog"}]
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(&*deck.name, "Mixed");
+        assert_eq!(deck.pairs[0].matches.len(), 2);
+        assert_eq!(
+            deck.pairs[1].matches[1].item,
+            Item::Image("/images/dog.png".into())
+        );
+        assert_eq!(deck.pairs[1].matches[1].label(), "a dog");
+    }
+
+    #[test]
+    fn unknown_fields_are_named() {
+        let err = deck(r#"{"name": "Typo", "pairs": [{"txt": "a"}]}"#).unwrap_err();
+        assert!(
+            matches!(&err, DeckError::Parse(message) if message.contains("`txt`")),
+            "{err:?}"
+        );
+
+        let err =
+            deck(r#"{"name": "Typo", "pairs": [[{"text": "a"}, {"emjoi": "b"}]]}"#).unwrap_err();
+        assert!(
+            matches!(&err, DeckError::Parse(message) if message.contains("`emjoi`")),
+            "{err:?}"
+        );
+
+        let err =
+            deck(r#"{"name": "Typo", "groupsize": 3, "pairs": [{"text": "a"}]}"#).unwrap_err();
+        assert!(
+            matches!(&err, DeckError::Parse(message) if message.contains("`groupsize`")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn malformed_json_is_a_parse_error() {
+        assert!(matches!(deck("{"), Err(DeckError::Parse(_))));
+        assert!(matches!(
+            deck(r#"{"pairs": [{"text": "a"}]}"#),
+            Err(DeckError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn deck_file_errors() {
+        let cases = [
+            (
+                r#"{"name": " ", "pairs": [{"text": "a"}]}"#,
+                DeckError::MissingName,
+            ),
+            (r#"{"name": "Empty", "pairs": []}"#, DeckError::NoPairs),
+            (
+                r#"{"name": "Solo", "group_size": 1, "pairs": [{"text": "a"}]}"#,
+                DeckError::InvalidGroupSize(1),
+            ),
+            (
+                r#"{"name": "Blank", "pairs": [{"text": "a"}, {"audio": "a.wav"}]}"#,
+                DeckError::EmptyCard { pair: 1 },
+            ),
+            (
+                r#"{"name": "Both", "pairs": [{"text": "a", "emoji": "🐶"}]}"#,
+                DeckError::AmbiguousCard { pair: 0 },
+            ),
+            (
+                r#"{"name": "Twice", "pairs": [{"text": "a"}, [{"text": "b"}, {"text": "a"}]]}"#,
+                DeckError::DuplicatePair { pair: 1 },
+            ),
+            (
+                r#"{"name": "Short", "group_size": 3, "pairs": [[{"text": "a"}, {"text": "b"}]]}"#,
+                DeckError::WrongGroupSize {
+                    pair: 0,
+                    expected: 3,
+                    found: 2,
+                },
+            ),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(deck(json).unwrap_err(), expected, "{json}");
+        }
+    }
+
+    #[test]
+    fn running_out_of_pairs_is_an_error() {
+        assert_eq!(
+            take_pairs(symbol_pairs(2).take(3), 4).unwrap_err(),
+            DeckError::NotEnoughPairs {
+                requested: 4,
+                available: 3
+            }
+        );
+    }
+}
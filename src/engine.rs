@@ -0,0 +1,742 @@
+//! The rules of a memory match, independent of any UI.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum CardState {
+    #[default]
+    Unselected,
+    Selected,
+    Failure,
+    Success,
+    Hidden,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FlipOutcome {
+    /// The card can't be flipped right now, nothing changed.
+    Ignored,
+    /// The card was added to a selection that isn't complete yet.
+    Selected,
+    Match,
+    Mismatch,
+}
+
+#[derive(Clone, Debug)]
+struct BoardCard<T> {
+    pair: usize,
+    item: T,
+    state: CardState,
+}
+
+/// A card flip the board accepted, `at_ms` is counted from the match's first flip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Flip {
+    pub card: usize,
+    pub at_ms: u64,
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("Flip {0} happened before the one preceding it")]
+    OutOfOrder(usize),
+    #[error("Flip {0} isn't allowed at that point of the match")]
+    IllegalFlip(usize),
+    #[error("Flip {0} happened after the board was cleared")]
+    AfterWin(usize),
+    #[error("The board was never cleared")]
+    Unfinished,
+    #[error("Not a replay: {0}")]
+    Parse(String),
+    #[error("Flip {0} happened after the match was lost")]
+    Lost(usize),
+    #[error("Flip {0} came too soon after the one preceding it")]
+    TooFast(usize),
+}
+
+/// Closest two flips can follow each other, anything faster wasn't played by hand.
+pub const MIN_FLIP_GAP: Duration = Duration::from_millis(50);
+
+/// Ways to lose a match, clearing the board is always how it's won.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rules {
+    /// Take as long as needed.
+    #[default]
+    Relaxed,
+    /// The board has to be cleared within `limit`, every match adds `bonus` to it.
+    Countdown { limit: Duration, bonus: Duration },
+    /// Every mismatch costs one of the lives, the match is over once they're gone.
+    Lives(u32),
+}
+
+impl Rules {
+    /// How long until the countdown runs out, if there is one.
+    pub fn time_left(&self, stats: &Stats) -> Option<Duration> {
+        match *self {
+            Rules::Relaxed => None,
+            Rules::Countdown { limit, bonus } => {
+                let matches = stats.moves - stats.mismatches;
+                Some((limit + bonus * matches).saturating_sub(stats.elapsed))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn lives_left(&self, stats: &Stats) -> Option<u32> {
+        match *self {
+            Rules::Lives(lives) => Some(lives.saturating_sub(stats.mismatches)),
+            _ => None,
+        }
+    }
+
+    pub fn is_lost(&self, stats: &Stats) -> bool {
+        self.time_left(stats) == Some(Duration::ZERO) || self.lives_left(stats) == Some(0)
+    }
+}
+
+/// How well a match is going.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of completed attempts at a group, matched or not.
+    pub moves: u32,
+    pub mismatches: u32,
+    pub elapsed: Duration,
+    pub score: u32,
+}
+
+/// The cards of a single match, in the order they are laid out.
+#[derive(Clone, Debug)]
+pub struct Board<T> {
+    cards: Vec<BoardCard<T>>,
+    group_size: usize,
+    selected: Vec<usize>,
+    cards_left: usize,
+    moves: u32,
+    mismatches: u32,
+}
+
+impl<T> Board<T> {
+    /// Takes `(pair, item)` for every card, `group_size` cards of the same pair make a match.
+    pub fn new(cards: impl IntoIterator<Item = (usize, T)>, group_size: usize) -> Self {
+        let cards: Vec<_> = cards
+            .into_iter()
+            .map(|(pair, item)| BoardCard {
+                pair,
+                item,
+                state: CardState::default(),
+            })
+            .collect();
+        let cards_left = cards.len();
+
+        Self {
+            cards,
+            group_size,
+            selected: Vec::new(),
+            cards_left,
+            moves: 0,
+            mismatches: 0,
+        }
+    }
+
+    /// Lays out the cards in an order that only depends on `seed`, the same in the browser and on
+    /// the server.
+    pub fn deal(cards: impl IntoIterator<Item = (usize, T)>, group_size: usize, seed: u64) -> Self {
+        let mut cards: Vec<_> = cards.into_iter().collect();
+        // a Fisher-Yates of our own, `Rng::shuffle` draws `usize`s and so deals differently on
+        // wasm32 than on a 64-bit server, and fastrand is pinned since `u32` may change with it
+        let mut rng = fastrand::Rng::with_seed(seed);
+        for i in (1..cards.len()).rev() {
+            cards.swap(i, rng.u32(..=i as u32) as usize);
+        }
+        Self::new(cards, group_size)
+    }
+
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn item(&self, id: usize) -> &T {
+        &self.cards[id].item
+    }
+
+    pub fn pair(&self, id: usize) -> usize {
+        self.cards[id].pair
+    }
+
+    pub fn state(&self, id: usize) -> CardState {
+        self.cards[id].state
+    }
+
+    pub fn cards_left(&self) -> usize {
+        self.cards_left
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.cards_left == 0
+    }
+
+    /// 100 points per group, minus 10 per mismatch and 1 per second, never below zero.
+    pub fn stats(&self, elapsed: Duration) -> Stats {
+        let groups = (self.cards.len() / self.group_size) as u64;
+        let score = (groups * 100)
+            .saturating_sub(self.mismatches as u64 * 10)
+            .saturating_sub(elapsed.as_secs());
+
+        Stats {
+            moves: self.moves,
+            mismatches: self.mismatches,
+            elapsed,
+            score: score as u32,
+        }
+    }
+
+    pub fn flip(&mut self, id: usize) -> FlipOutcome {
+        match self.cards.get(id).map(|card| card.state) {
+            Some(CardState::Unselected | CardState::Failure) => {}
+            _ => return FlipOutcome::Ignored,
+        }
+
+        // a finished selection that's still revealed goes away once the next card is flipped
+        self.settle();
+
+        let Some(&other) = self.selected.last() else {
+            self.cards[id].state = CardState::Selected;
+            self.selected.push(id);
+            return FlipOutcome::Selected;
+        };
+        self.selected.push(id);
+
+        if self.cards[other].pair != self.cards[id].pair {
+            self.set_selected(CardState::Failure);
+            self.moves += 1;
+            self.mismatches += 1;
+            return FlipOutcome::Mismatch;
+        }
+
+        if self.selected.len() < self.group_size {
+            self.cards[id].state = CardState::Selected;
+            return FlipOutcome::Selected;
+        }
+
+        self.set_selected(CardState::Success);
+        self.cards_left -= self.selected.len();
+        self.moves += 1;
+        FlipOutcome::Match
+    }
+
+    /// Turns a mismatch back over and clears a match away, returns whether there was one to settle.
+    ///
+    /// Flipping does this anyway, settling early only changes what's shown in the meantime.
+    pub fn settle(&mut self) -> bool {
+        if !self.is_resolved() {
+            return false;
+        }
+
+        for &selected in &self.selected {
+            let state = &mut self.cards[selected].state;
+            *state = match *state {
+                CardState::Failure => CardState::Unselected,
+                CardState::Success => CardState::Hidden,
+                x => x,
+            };
+        }
+        self.selected.clear();
+        true
+    }
+
+    /// Turns a selection that isn't complete yet back over, for when whoever picked it can't finish
+    /// it, returns whether there was one.
+    pub fn unselect(&mut self) -> bool {
+        if self.selected.is_empty() || self.is_resolved() {
+            return false;
+        }
+        self.set_selected(CardState::Unselected);
+        self.selected.clear();
+        true
+    }
+
+    /// Plays a whole match from a fresh board, rejecting anything a real player couldn't have done.
+    pub fn replay(&mut self, rules: Rules, flips: &[Flip]) -> Result<Stats, ReplayError> {
+        let mut last_at = 0;
+        for (index, flip) in flips.iter().enumerate() {
+            if self.is_won() {
+                return Err(ReplayError::AfterWin(index));
+            }
+            if flip.at_ms < last_at {
+                return Err(ReplayError::OutOfOrder(index));
+            }
+            if index > 0 && flip.at_ms < last_at + MIN_FLIP_GAP.as_millis() as u64 {
+                return Err(ReplayError::TooFast(index));
+            }
+            last_at = flip.at_ms;
+            if rules.is_lost(&self.stats(Duration::from_millis(flip.at_ms))) {
+                return Err(ReplayError::Lost(index));
+            }
+            if self.flip(flip.card) == FlipOutcome::Ignored {
+                return Err(ReplayError::IllegalFlip(index));
+            }
+        }
+
+        if !self.is_won() {
+            return Err(ReplayError::Unfinished);
+        }
+        Ok(self.stats(Duration::from_millis(last_at)))
+    }
+
+    fn is_resolved(&self) -> bool {
+        self.selected.last().is_some_and(|&id| {
+            matches!(
+                self.cards[id].state,
+                CardState::Failure | CardState::Success
+            )
+        })
+    }
+
+    fn set_selected(&mut self, state: CardState) {
+        for &selected in &self.selected {
+            self.cards[selected].state = state;
+        }
+    }
+}
+
+/// Most players that can share a board.
+pub const MAX_PLAYERS: usize = 4;
+
+/// Players sharing one board, a match scores and plays again while a mismatch passes the turn.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turns {
+    scores: Vec<u32>,
+    current: usize,
+    /// Players who left, in the order they did.
+    #[serde(default)]
+    left: Vec<usize>,
+}
+
+impl Turns {
+    pub fn new(players: usize) -> Self {
+        Self {
+            scores: vec![0; players.max(1)],
+            current: 0,
+            left: Vec::new(),
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Seats someone new after the others, they play once everyone before them had a turn.
+    pub fn add_player(&mut self) {
+        self.scores.push(0);
+    }
+
+    /// Takes `player` out of the rotation, passing the turn on if it was theirs, the matches they
+    /// found still count.
+    pub fn remove_player(&mut self, player: usize) {
+        if player >= self.players() || self.has_left(player) {
+            return;
+        }
+        self.left.push(player);
+        if self.current == player {
+            self.pass();
+        }
+    }
+
+    pub fn has_left(&self, player: usize) -> bool {
+        self.left.contains(&player)
+    }
+
+    /// Players still taking turns.
+    pub fn playing(&self) -> usize {
+        self.players() - self.left.len()
+    }
+
+    /// Whose turn it is, counted from 0.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Matches found by each player.
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+
+    pub fn record(&mut self, outcome: FlipOutcome) {
+        match outcome {
+            FlipOutcome::Match => self.scores[self.current] += 1,
+            FlipOutcome::Mismatch => self.pass(),
+            FlipOutcome::Ignored | FlipOutcome::Selected => {}
+        }
+    }
+
+    // to the next player still there, the turn stays put once everyone has left
+    fn pass(&mut self) {
+        let players = self.players();
+        if let Some(next) = (1..=players)
+            .map(|offset| (self.current + offset) % players)
+            .find(|&player| !self.has_left(player))
+        {
+            self.current = next;
+        }
+    }
+
+    /// Everyone with the top score, more than one means a tie.
+    pub fn leaders(&self) -> Vec<usize> {
+        let top = self.scores.iter().copied().max().unwrap_or_default();
+        (0..self.players())
+            .filter(|&player| self.scores[player] == top)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a b a b, laid out as given
+    fn board() -> Board<char> {
+        Board::new([(0, 'a'), (1, 'b'), (0, 'a'), (1, 'b')], 2)
+    }
+
+    fn layout(cards: usize, seed: u64) -> Vec<usize> {
+        let board = Board::deal((0..cards).map(|card| (card, card)), 2, seed);
+        (0..cards).map(|id| *board.item(id)).collect()
+    }
+
+    // the browser and the server deal on their own, and stored seeds and replays expect the
+    // layout they were played on, so this must never change
+    #[test]
+    fn deals_are_pinned() {
+        assert_eq!(layout(6, 1), [1, 3, 2, 4, 0, 5]);
+        assert_eq!(
+            layout(20, 20240309),
+            [5, 10, 9, 19, 11, 15, 14, 17, 12, 2, 16, 1, 3, 13, 8, 7, 0, 4, 18, 6]
+        );
+    }
+
+    #[test]
+    fn deals_depend_on_the_seed() {
+        assert_eq!(layout(20, 7), layout(20, 7));
+        assert_ne!(layout(20, 7), layout(20, 8));
+    }
+
+    #[test]
+    fn first_card_of_a_group_is_selected() {
+        let mut board = board();
+        assert_eq!(board.flip(0), FlipOutcome::Selected);
+        assert_eq!(board.state(0), CardState::Selected);
+    }
+
+    #[test]
+    fn same_pair_matches() {
+        let mut board = board();
+        board.flip(0);
+        assert_eq!(board.flip(2), FlipOutcome::Match);
+        assert_eq!(board.state(0), CardState::Success);
+        assert_eq!(board.state(2), CardState::Success);
+        assert_eq!(board.cards_left(), 2);
+        assert_eq!(board.stats(Duration::ZERO).moves, 1);
+    }
+
+    #[test]
+    fn different_pairs_mismatch() {
+        let mut board = board();
+        board.flip(0);
+        assert_eq!(board.flip(1), FlipOutcome::Mismatch);
+        assert_eq!(board.state(0), CardState::Failure);
+        assert_eq!(board.state(1), CardState::Failure);
+        assert_eq!(board.cards_left(), 4);
+
+        let stats = board.stats(Duration::ZERO);
+        assert_eq!((stats.moves, stats.mismatches), (1, 1));
+    }
+
+    #[test]
+    fn larger_groups_stay_selected_until_complete() {
+        let mut board = Board::new([(0, 'a'), (0, 'a'), (0, 'a'), (1, 'b')], 3);
+        assert_eq!(board.flip(0), FlipOutcome::Selected);
+        assert_eq!(board.flip(1), FlipOutcome::Selected);
+        assert_eq!(board.flip(2), FlipOutcome::Match);
+    }
+
+    #[test]
+    fn selected_card_is_ignored() {
+        let mut board = board();
+        board.flip(0);
+        assert_eq!(board.flip(0), FlipOutcome::Ignored);
+        assert_eq!(board.state(0), CardState::Selected);
+    }
+
+    #[test]
+    fn matched_card_is_ignored() {
+        let mut board = board();
+        board.flip(0);
+        board.flip(2);
+        assert_eq!(board.flip(0), FlipOutcome::Ignored);
+        assert_eq!(board.state(0), CardState::Success);
+
+        board.settle();
+        assert_eq!(board.flip(0), FlipOutcome::Ignored);
+        assert_eq!(board.state(0), CardState::Hidden);
+    }
+
+    #[test]
+    fn out_of_range_card_is_ignored() {
+        assert_eq!(board().flip(4), FlipOutcome::Ignored);
+    }
+
+    #[test]
+    fn failed_card_can_be_flipped_again() {
+        let mut board = board();
+        board.flip(0);
+        board.flip(1);
+        assert_eq!(board.flip(0), FlipOutcome::Selected);
+        assert_eq!(board.state(0), CardState::Selected);
+        // the other half of the mismatch was turned back over
+        assert_eq!(board.state(1), CardState::Unselected);
+        assert_eq!(board.flip(2), FlipOutcome::Match);
+    }
+
+    #[test]
+    fn clearing_every_pair_wins() {
+        let mut board = board();
+        assert!(!board.is_won());
+        board.flip(0);
+        board.flip(2);
+        assert!(!board.is_won());
+        board.flip(1);
+        board.flip(3);
+        assert_eq!(board.cards_left(), 0);
+        assert!(board.is_won());
+    }
+
+    #[test]
+    fn settle_turns_back_mismatches_and_hides_matches() {
+        let mut board = board();
+        board.flip(0);
+        board.flip(1);
+        assert!(board.settle());
+        assert_eq!(board.state(0), CardState::Unselected);
+        assert_eq!(board.state(1), CardState::Unselected);
+
+        board.flip(0);
+        board.flip(2);
+        assert!(board.settle());
+        assert_eq!(board.state(0), CardState::Hidden);
+        assert_eq!(board.state(2), CardState::Hidden);
+    }
+
+    #[test]
+    fn settle_leaves_incomplete_selections_alone() {
+        let mut board = board();
+        assert!(!board.settle());
+        board.flip(0);
+        assert!(!board.settle());
+        assert_eq!(board.state(0), CardState::Selected);
+    }
+
+    fn played(cards_and_times: &[(usize, u64)]) -> Vec<Flip> {
+        cards_and_times
+            .iter()
+            .map(|&(card, at_ms)| Flip { card, at_ms })
+            .collect()
+    }
+
+    #[test]
+    fn valid_replay_gives_the_final_stats() {
+        let flips = played(&[(0, 0), (1, 100), (0, 200), (2, 300), (1, 400), (3, 2500)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Ok(Stats {
+                moves: 3,
+                mismatches: 1,
+                elapsed: Duration::from_millis(2500),
+                score: 200 - 10 - 2,
+            })
+        );
+    }
+
+    #[test]
+    fn replay_rejects_flips_going_back_in_time() {
+        let flips = played(&[(0, 100), (2, 50), (1, 200), (3, 300)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::OutOfOrder(1))
+        );
+    }
+
+    #[test]
+    fn replay_rejects_flips_no_one_could_make() {
+        let flips = played(&[(0, 0), (2, 100), (1, 120), (3, 300)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::TooFast(2))
+        );
+
+        // the forged perfect game, every flip at once
+        let flips = played(&[(0, 0), (2, 0), (1, 0), (3, 0)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::TooFast(1))
+        );
+    }
+
+    #[test]
+    fn replay_rejects_flips_the_board_ignores() {
+        let flips = played(&[(0, 0), (0, 100), (2, 200), (1, 300), (3, 400)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::IllegalFlip(1))
+        );
+
+        let missing_card = played(&[(0, 0), (9, 100)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &missing_card),
+            Err(ReplayError::IllegalFlip(1))
+        );
+    }
+
+    #[test]
+    fn replay_rejects_flips_after_the_win() {
+        let flips = played(&[(0, 0), (2, 100), (1, 200), (3, 300), (0, 400)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::AfterWin(4))
+        );
+    }
+
+    #[test]
+    fn replay_must_clear_the_board() {
+        let flips = played(&[(0, 0), (2, 100), (1, 200)]);
+        assert_eq!(
+            board().replay(Rules::Relaxed, &flips),
+            Err(ReplayError::Unfinished)
+        );
+        assert_eq!(
+            board().replay(Rules::Relaxed, &[]),
+            Err(ReplayError::Unfinished)
+        );
+    }
+
+    fn stats(moves: u32, mismatches: u32, elapsed_secs: u64) -> Stats {
+        Stats {
+            moves,
+            mismatches,
+            elapsed: Duration::from_secs(elapsed_secs),
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn relaxed_never_loses() {
+        let stats = stats(50, 40, 3600);
+        assert_eq!(Rules::Relaxed.time_left(&stats), None);
+        assert_eq!(Rules::Relaxed.lives_left(&stats), None);
+        assert!(!Rules::Relaxed.is_lost(&stats));
+    }
+
+    #[test]
+    fn countdown_gains_time_per_match() {
+        let rules = Rules::Countdown {
+            limit: Duration::from_secs(10),
+            bonus: Duration::from_secs(3),
+        };
+        // one match and one mismatch, 13 seconds in total
+        assert_eq!(
+            rules.time_left(&stats(2, 1, 0)),
+            Some(Duration::from_secs(13))
+        );
+        assert_eq!(
+            rules.time_left(&stats(2, 1, 12)),
+            Some(Duration::from_secs(1))
+        );
+        assert!(!rules.is_lost(&stats(2, 1, 12)));
+        assert_eq!(rules.time_left(&stats(2, 1, 13)), Some(Duration::ZERO));
+        assert!(rules.is_lost(&stats(2, 1, 13)));
+        assert_eq!(rules.time_left(&stats(2, 1, 60)), Some(Duration::ZERO));
+        assert_eq!(rules.lives_left(&stats(2, 1, 0)), None);
+    }
+
+    #[test]
+    fn lives_run_out_with_mismatches() {
+        let rules = Rules::Lives(2);
+        assert_eq!(rules.lives_left(&stats(5, 0, 0)), Some(2));
+        assert_eq!(rules.lives_left(&stats(5, 1, 0)), Some(1));
+        assert!(!rules.is_lost(&stats(5, 1, 0)));
+        assert_eq!(rules.lives_left(&stats(5, 2, 0)), Some(0));
+        assert!(rules.is_lost(&stats(5, 2, 0)));
+        assert_eq!(rules.lives_left(&stats(5, 3, 0)), Some(0));
+        assert_eq!(rules.time_left(&stats(5, 1, 600)), None);
+    }
+
+    #[test]
+    fn replay_rejects_flips_after_the_countdown() {
+        let rules = Rules::Countdown {
+            limit: Duration::from_secs(1),
+            bonus: Duration::from_secs(1),
+        };
+        // the match at 500 ms buys another second
+        let in_time = played(&[(0, 0), (2, 500), (1, 1500), (3, 1900)]);
+        assert!(board().replay(rules, &in_time).is_ok());
+
+        let too_late = played(&[(0, 0), (2, 500), (1, 2000), (3, 2100)]);
+        assert_eq!(board().replay(rules, &too_late), Err(ReplayError::Lost(2)));
+    }
+
+    #[test]
+    fn replay_rejects_flips_after_the_last_life() {
+        let flips = played(&[(0, 0), (1, 100), (0, 200), (2, 300), (1, 400), (3, 500)]);
+        assert!(board().replay(Rules::Lives(2), &flips).is_ok());
+        assert_eq!(
+            board().replay(Rules::Lives(1), &flips),
+            Err(ReplayError::Lost(2))
+        );
+    }
+
+    #[test]
+    fn turns_skip_players_who_left() {
+        let mut turns = Turns::new(3);
+        turns.record(FlipOutcome::Match);
+        turns.remove_player(0);
+        assert_eq!(turns.current(), 1);
+        assert_eq!(turns.playing(), 2);
+        assert_eq!(turns.scores(), [1, 0, 0]);
+
+        turns.record(FlipOutcome::Mismatch);
+        assert_eq!(turns.current(), 2);
+        turns.record(FlipOutcome::Mismatch);
+        assert_eq!(turns.current(), 1);
+
+        turns.remove_player(2);
+        turns.record(FlipOutcome::Mismatch);
+        assert_eq!(turns.current(), 1);
+        assert_eq!(turns.playing(), 1);
+    }
+
+    #[test]
+    fn unselecting_turns_back_an_incomplete_selection() {
+        let mut board = board();
+        assert!(!board.unselect());
+
+        board.flip(0);
+        assert!(board.unselect());
+        assert_eq!(board.state(0), CardState::Unselected);
+        // the next flip starts a selection of its own rather than completing the dropped one
+        assert_eq!(board.flip(2), FlipOutcome::Selected);
+        assert_eq!(board.stats(Duration::ZERO).moves, 0);
+
+        assert_eq!(board.flip(1), FlipOutcome::Mismatch);
+        assert!(!board.unselect());
+        assert_eq!(board.state(1), CardState::Failure);
+    }
+}
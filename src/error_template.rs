@@ -1,3 +1,4 @@
+use crate::{deck::DeckError, engine::ReplayError};
 use cfg_if::cfg_if;
 use http::status::StatusCode;
 use leptos::*;
@@ -10,12 +11,27 @@ use leptos_axum::ResponseOptions;
 pub enum AppError {
     #[error("Not Found")]
     NotFound,
+    #[error(transparent)]
+    Deck(#[from] DeckError),
+    #[error("Invalid replay: {0}")]
+    InvalidReplay(#[from] ReplayError),
+    #[error("This game wasn't started on the server, or was already submitted")]
+    UnknownGame,
+    #[error("The flips took longer than the game did")]
+    ClockMismatch,
+    #[error("That daily challenge isn't open any more")]
+    NotToday,
 }
 
 impl AppError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Deck(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidReplay(_) | AppError::ClockMismatch | AppError::NotToday => {
+                StatusCode::BAD_REQUEST
+            }
+            AppError::UnknownGame => StatusCode::NOT_FOUND,
         }
     }
 }
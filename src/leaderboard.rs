@@ -0,0 +1,431 @@
+#[cfg(feature = "ssr")]
+use crate::error_template::AppError;
+use crate::{engine::Flip, records::GameKind};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+/// How many scores `get_scores` returns.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Largest board the server will replay.
+pub const MAX_SIZE: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub kind: GameKind,
+    pub seed: u64,
+    pub moves: u32,
+    pub mismatches: u32,
+    pub time_ms: u64,
+    pub score: u32,
+    /// Dealt on a seed the player asked for, whose layout they might have known, so the score only
+    /// counts on that seed's own leaderboard.
+    #[serde(default)]
+    pub seed_chosen: bool,
+}
+
+/// A game the server dealt and is timing, only these can be submitted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ticket {
+    pub token: String,
+    pub seed: u64,
+}
+
+/// Deals `kind` with `seed`, or a fresh one, and starts the server's clock.
+///
+/// Asked for on the first flip, so the layout isn't known before the clock runs.
+#[server(StartGame, "/api")]
+pub async fn start_game(kind: GameKind, seed: Option<u64>) -> Result<Ticket, ServerFnError> {
+    verify::pairs(&kind).map_err(server_error)?;
+    let daily = kind == crate::daily::kind();
+    // everyone gets the same daily board, any other layout is only unknown if the server picks it
+    let seed_chosen = seed.is_some() && !daily;
+    let seed = match seed {
+        Some(seed) => seed,
+        None if daily => crate::daily::seed_at(std::time::SystemTime::now()),
+        None => fastrand::u64(..),
+    };
+    verify::started_on_time(&kind, seed).map_err(server_error)?;
+    Ok(tickets::issue(kind, seed, seed_chosen))
+}
+
+/// Stops the server's clock once the board is cleared, before the player gets to the name.
+#[server(FinishGame, "/api")]
+pub async fn finish_game(token: String) -> Result<(), ServerFnError> {
+    tickets::finish(&token).map_err(server_error)
+}
+
+/// Replays `flips` on the board dealt for the ticket and keeps the result, timed by the server.
+#[server(SubmitScore, "/api")]
+pub async fn submit_score(
+    name: String,
+    token: String,
+    flips: Vec<Flip>,
+) -> Result<ScoreEntry, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 20 {
+        return Err(ServerFnError::ServerError(
+            "Name must be between 1 and 20 characters".to_string(),
+        ));
+    }
+
+    let game = tickets::claim(&token).map_err(server_error)?;
+    let stored = match verify::submission(name, game, &flips) {
+        Ok(entry) => store::insert(entry.clone())
+            .await
+            .map(|()| entry)
+            .map_err(|err| ServerFnError::ServerError(err.to_string())),
+        Err(err) => Err(server_error(err)),
+    };
+    // only a stored score uses the game up, after anything else it can be submitted again
+    match stored {
+        Ok(_) => tickets::remove(&token),
+        Err(_) => tickets::release(&token),
+    }
+    stored
+}
+
+/// Best scores first, only from games dealt with `seed` if there is one.
+#[server(GetScores, "/api")]
+pub async fn get_scores(
+    kind: GameKind,
+    seed: Option<u64>,
+) -> Result<Vec<ScoreEntry>, ServerFnError> {
+    store::top(&kind, seed, LEADERBOARD_SIZE)
+        .await
+        .map_err(|err| ServerFnError::ServerError(err.to_string()))
+}
+
+#[cfg(feature = "ssr")]
+fn server_error(err: AppError) -> ServerFnError {
+    if let Some(response) = use_context::<leptos_axum::ResponseOptions>() {
+        response.set_status(err.status_code());
+    }
+    ServerFnError::ServerError(err.to_string())
+}
+
+#[cfg(feature = "ssr")]
+mod verify {
+    use super::{tickets::Finished, ScoreEntry, MAX_SIZE};
+    use crate::{
+        daily,
+        deck::{
+            self, classic_group_size, is_valid_id, symbol_pairs, take_pairs, Deck, DeckError, Pairs,
+        },
+        engine::{Flip, ReplayError},
+        error_template::AppError,
+        records::GameKind,
+    };
+    use std::time::{Duration, SystemTime};
+
+    /// How far the flips' own timing may run past the server's, for timers that tick differently.
+    const CLOCK_SLACK: Duration = Duration::from_secs(1);
+
+    pub fn submission(name: &str, game: Finished, flips: &[Flip]) -> Result<ScoreEntry, AppError> {
+        submitted_on_time(&game)?;
+        let rules = game.kind.rules();
+        let mut board = deck::deal(&pairs(&game.kind)?, game.seed);
+        let claimed = board.replay(rules, flips)?;
+
+        // the flips can't have taken longer than the game, and a faster claim isn't believed
+        if claimed.elapsed > game.took + CLOCK_SLACK {
+            return Err(AppError::ClockMismatch);
+        }
+        if rules.is_lost(&board.stats(game.took.saturating_sub(CLOCK_SLACK))) {
+            return Err(ReplayError::Lost(flips.len().saturating_sub(1)).into());
+        }
+        let stats = board.stats(game.took.max(claimed.elapsed));
+
+        Ok(ScoreEntry {
+            name: name.to_string(),
+            kind: game.kind,
+            seed: game.seed,
+            moves: stats.moves,
+            mismatches: stats.mismatches,
+            time_ms: stats.elapsed.as_millis() as u64,
+            score: stats.score,
+            seed_chosen: game.seed_chosen,
+        })
+    }
+
+    /// Daily boards are only dealt on their own day.
+    pub fn started_on_time(kind: &GameKind, seed: u64) -> Result<(), AppError> {
+        if *kind == daily::kind() && seed != daily::seed_at(SystemTime::now()) {
+            return Err(AppError::NotToday);
+        }
+        Ok(())
+    }
+
+    /// A daily game begun before midnight can still be handed in the day after, not any later.
+    fn submitted_on_time(game: &Finished) -> Result<(), AppError> {
+        let next_day = daily::seed_at(game.issued + Duration::from_secs(24 * 60 * 60));
+        if game.kind == daily::kind() && daily::seed_at(SystemTime::now()) > next_day {
+            return Err(AppError::NotToday);
+        }
+        Ok(())
+    }
+
+    // the same pairs the client dealt from
+    pub fn pairs(kind: &GameKind) -> Result<Pairs, AppError> {
+        if kind.size > MAX_SIZE {
+            return Err(AppError::NotFound);
+        }
+        if let Some(group_size) = classic_group_size(&kind.mode) {
+            return Ok(take_pairs(symbol_pairs(group_size), kind.size)?);
+        }
+        if *kind == daily::kind() {
+            return Ok(daily::pairs()?);
+        }
+
+        let id = kind.mode.strip_prefix("deck/").ok_or(AppError::NotFound)?;
+        if !is_valid_id(id) {
+            return Err(DeckError::InvalidId(id.to_string()).into());
+        }
+        let site_root = std::env::var("LEPTOS_SITE_ROOT").unwrap_or_else(|_| "target/site".into());
+        let json = std::fs::read_to_string(format!("{site_root}/decks/{id}.json"))
+            .map_err(|err| DeckError::Fetch(err.to_string()))?;
+        let deck = Deck::from_json(id, &json)?;
+        if deck.pairs.len() != kind.size {
+            return Err(AppError::NotFound);
+        }
+        Ok(deck.pairs)
+    }
+}
+
+#[cfg(feature = "ssr")]
+mod tickets {
+    use super::Ticket;
+    use crate::{error_template::AppError, records::GameKind};
+    use std::{
+        collections::HashMap,
+        sync::{Mutex, OnceLock},
+        time::{Duration, Instant, SystemTime},
+    };
+
+    /// Games not submitted by then are forgotten.
+    const LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+    struct Started {
+        kind: GameKind,
+        seed: u64,
+        seed_chosen: bool,
+        at: Instant,
+        /// The date of `at`, which an `Instant` can't tell.
+        issued: SystemTime,
+        finished_at: Option<Instant>,
+        /// While a submission is being checked and stored.
+        claimed: bool,
+    }
+
+    pub struct Finished {
+        pub kind: GameKind,
+        pub seed: u64,
+        pub seed_chosen: bool,
+        pub issued: SystemTime,
+        /// From dealing the board to clearing it, by the server's clock.
+        pub took: Duration,
+    }
+
+    static TICKETS: OnceLock<Mutex<HashMap<String, Started>>> = OnceLock::new();
+
+    fn tickets() -> std::sync::MutexGuard<'static, HashMap<String, Started>> {
+        TICKETS.get_or_init(Default::default).lock().unwrap()
+    }
+
+    pub fn issue(kind: GameKind, seed: u64, seed_chosen: bool) -> Ticket {
+        let token = format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..));
+        let mut tickets = tickets();
+        tickets.retain(|_, started| started.at.elapsed() < LIFETIME);
+        tickets.insert(
+            token.clone(),
+            Started {
+                kind,
+                seed,
+                seed_chosen,
+                at: Instant::now(),
+                issued: SystemTime::now(),
+                finished_at: None,
+                claimed: false,
+            },
+        );
+        Ticket { token, seed }
+    }
+
+    /// Only the first call counts.
+    pub fn finish(token: &str) -> Result<(), AppError> {
+        let mut tickets = tickets();
+        let started = tickets.get_mut(token).ok_or(AppError::UnknownGame)?;
+        started.finished_at.get_or_insert_with(Instant::now);
+        Ok(())
+    }
+
+    /// Holds the ticket for one submission at a time, a game never reported finished ends now.
+    pub fn claim(token: &str) -> Result<Finished, AppError> {
+        let mut tickets = tickets();
+        let started = tickets
+            .get_mut(token)
+            .filter(|started| !started.claimed)
+            .ok_or(AppError::UnknownGame)?;
+        started.claimed = true;
+        let finished_at = *started.finished_at.get_or_insert_with(Instant::now);
+        Ok(Finished {
+            kind: started.kind.clone(),
+            seed: started.seed,
+            seed_chosen: started.seed_chosen,
+            issued: started.issued,
+            took: finished_at - started.at,
+        })
+    }
+
+    /// Lets the ticket be submitted again after a submission that didn't go through.
+    pub fn release(token: &str) {
+        if let Some(started) = tickets().get_mut(token) {
+            started.claimed = false;
+        }
+    }
+
+    /// Every ticket is good for one score.
+    pub fn remove(token: &str) {
+        tickets().remove(token);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn tickets_are_held_until_a_score_is_stored() {
+            let kind = GameKind::new("classic", 8);
+            let ticket = issue(kind.clone(), 7, true);
+            let game = claim(&ticket.token).unwrap();
+            assert_eq!((game.kind, game.seed, game.seed_chosen), (kind, 7, true));
+            assert!(matches!(claim(&ticket.token), Err(AppError::UnknownGame)));
+
+            release(&ticket.token);
+            let again = claim(&ticket.token).unwrap();
+            assert_eq!(again.took, game.took);
+
+            remove(&ticket.token);
+            release(&ticket.token);
+            assert!(matches!(claim(&ticket.token), Err(AppError::UnknownGame)));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+mod store {
+    use super::ScoreEntry;
+    use crate::records::GameKind;
+    use std::{io, path::PathBuf, sync::OnceLock};
+    use tokio::{
+        fs,
+        sync::{MappedMutexGuard, Mutex, MutexGuard},
+    };
+
+    // every score is kept in memory and the whole file rewritten on insert,
+    // plenty for a prototype's traffic
+    static SCORES: OnceLock<Mutex<Option<Vec<ScoreEntry>>>> = OnceLock::new();
+
+    fn path() -> PathBuf {
+        std::env::var_os("LEADERBOARD_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("leaderboard.json"))
+    }
+
+    /// Loads the file on first use, one that can't be read is retried next time rather than
+    /// treated as empty, so it never gets overwritten with only the newest score.
+    async fn scores() -> io::Result<MappedMutexGuard<'static, Vec<ScoreEntry>>> {
+        let mut scores = SCORES.get_or_init(|| Mutex::new(None)).lock().await;
+        if scores.is_none() {
+            *scores = Some(load().await?);
+        }
+        Ok(MutexGuard::map(scores, |scores| {
+            scores.get_or_insert_with(Vec::new)
+        }))
+    }
+
+    async fn load() -> io::Result<Vec<ScoreEntry>> {
+        match fs::read_to_string(path()).await {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn insert(entry: ScoreEntry) -> io::Result<()> {
+        // the lock is async, so waiting on the disk doesn't block the server's threads
+        let mut scores = scores().await?;
+        scores.push(entry);
+        let written = match serde_json::to_string(&*scores) {
+            Ok(json) => fs::write(path(), json).await,
+            Err(err) => Err(err.into()),
+        };
+        // what's in memory never gets ahead of the file
+        if written.is_err() {
+            scores.pop();
+        }
+        written
+    }
+
+    pub async fn top(
+        kind: &GameKind,
+        seed: Option<u64>,
+        limit: usize,
+    ) -> io::Result<Vec<ScoreEntry>> {
+        Ok(ranked(&scores().await?, kind, seed, limit))
+    }
+
+    /// Without a seed only games dealt on the server's seeds count.
+    fn ranked(
+        scores: &[ScoreEntry],
+        kind: &GameKind,
+        seed: Option<u64>,
+        limit: usize,
+    ) -> Vec<ScoreEntry> {
+        let mut top: Vec<_> = scores
+            .iter()
+            .filter(|entry| {
+                entry.kind == *kind && seed.map_or(!entry.seed_chosen, |seed| entry.seed == seed)
+            })
+            .cloned()
+            .collect();
+        top.sort_by_key(|entry| (std::cmp::Reverse(entry.score), entry.time_ms, entry.moves));
+        top.truncate(limit);
+        top
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn entry(seed: u64, score: u32, seed_chosen: bool) -> ScoreEntry {
+            ScoreEntry {
+                name: format!("{seed}"),
+                kind: GameKind::new("classic", 8),
+                seed,
+                moves: 8,
+                mismatches: 0,
+                time_ms: 10_000,
+                score,
+                seed_chosen,
+            }
+        }
+
+        #[test]
+        fn chosen_seeds_only_rank_on_their_own_board() {
+            let kind = GameKind::new("classic", 8);
+            let scores = [
+                entry(1, 700, false),
+                entry(2, 800, true),
+                entry(3, 600, false),
+            ];
+
+            let overall = ranked(&scores, &kind, None, 10);
+            assert_eq!(overall, [scores[0].clone(), scores[2].clone()]);
+            assert_eq!(ranked(&scores, &kind, Some(2), 10), [scores[1].clone()]);
+            assert_eq!(ranked(&scores, &kind, None, 1), [scores[0].clone()]);
+            assert!(ranked(&scores, &GameKind::new("classic", 9), None, 10).is_empty());
+        }
+    }
+}
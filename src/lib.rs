@@ -1,7 +1,17 @@
 use cfg_if::cfg_if;
 pub mod app;
+pub mod audio;
+pub mod daily;
+pub mod deck;
+pub mod engine;
 pub mod error_template;
 pub mod fileserv;
+pub mod leaderboard;
+pub mod online;
+pub mod records;
+pub mod replay;
+pub mod settings;
+pub mod storage;
 
 cfg_if! { if #[cfg(feature = "hydrate")] {
     use leptos::*;
@@ -0,0 +1,496 @@
+//! Rooms where players on different devices share one board.
+//!
+//! The server owns every room's board and turn order, clients only ask it to flip cards and
+//! redraw from the snapshots it broadcasts after each change.
+
+use crate::engine::{Board, FlipOutcome, Turns};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Where the socket is served.
+pub const PATH: &str = "/ws";
+
+/// Smallest and largest board a room can be opened with, in pairs.
+pub const SIZES: std::ops::RangeInclusive<usize> = 3..=20;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Opens a room with a classic board of `size` pairs.
+    Create {
+        name: String,
+        size: usize,
+    },
+    Join {
+        name: String,
+        code: String,
+    },
+    /// Follows a room without a seat, at any point of the match.
+    Watch {
+        code: String,
+    },
+    Flip {
+        card: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Only sent to whoever created or joined, `player` is their seat.
+    Joined {
+        player: usize,
+        room: RoomState,
+    },
+    /// Only sent to whoever started watching.
+    Watching {
+        room: RoomState,
+    },
+    /// Sent to everyone in the room after anything changed.
+    Update {
+        room: RoomState,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Everything needed to draw a room, however late someone tunes in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomState {
+    pub code: String,
+    /// Number of pairs, the board is dealt from the classic symbols.
+    pub size: usize,
+    pub seed: u64,
+    pub players: Vec<String>,
+    pub turns: Turns,
+    /// Everything the server did to the board, in order.
+    pub events: Vec<BoardEvent>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoardEvent {
+    Flip {
+        card: usize,
+    },
+    /// Whoever was picking cards left halfway, the ones they picked were turned back over.
+    Unselect,
+}
+
+impl BoardEvent {
+    /// `None` for anything that isn't a flip.
+    pub fn apply<T>(self, board: &mut Board<T>) -> Option<FlipOutcome> {
+        match self {
+            BoardEvent::Flip { card } => Some(board.flip(card)),
+            BoardEvent::Unselect => {
+                board.unselect();
+                None
+            }
+        }
+    }
+}
+
+impl RoomState {
+    /// Nobody can flip until a second player has joined.
+    pub fn is_waiting(&self) -> bool {
+        self.events.is_empty() && self.turns.playing() < 2
+    }
+
+    /// Everyone but one left after the first flip, nobody can join any more so the match is over.
+    pub fn is_abandoned(&self) -> bool {
+        !self.events.is_empty() && self.turns.playing() < 2
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum RoomError {
+    #[error("Name must be between 1 and 20 characters")]
+    InvalidName,
+    #[error("Rooms have between 3 and 20 pairs, not {0}")]
+    InvalidSize(usize),
+    #[error("There is no room {0}")]
+    NoSuchRoom(String),
+    #[error("The room is full")]
+    Full,
+    #[error("The match has already started")]
+    AlreadyStarted,
+    #[error("Already in a room")]
+    AlreadySeated,
+    #[error("Join a room first")]
+    NotSeated,
+    #[error("Waiting for another player")]
+    Waiting,
+    #[error("Everyone else left")]
+    Abandoned,
+    #[error("It's not your turn")]
+    NotYourTurn,
+    #[error("Spectators can't flip cards")]
+    Spectating,
+}
+
+/// The layout `deck::deal` gives the classic pairs, without the faces so it can cross threads.
+pub fn blank_board(size: usize, seed: u64) -> Board<()> {
+    let cards = (0..size).flat_map(|pair| [(pair, ()), (pair, ())]);
+    Board::deal(cards, 2, seed)
+}
+
+#[cfg(feature = "ssr")]
+pub mod server {
+    use super::{
+        blank_board, BoardEvent, ClientMessage, RoomError, RoomState, ServerMessage, SIZES,
+    };
+    use crate::engine::{Board, FlipOutcome, Turns, MAX_PLAYERS};
+    use axum::{
+        extract::ws::{Message, WebSocket, WebSocketUpgrade},
+        response::Response,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Mutex, OnceLock},
+    };
+    use tokio::sync::broadcast;
+
+    struct Room {
+        state: RoomState,
+        board: Board<()>,
+        updates: broadcast::Sender<RoomState>,
+    }
+
+    struct Seat {
+        code: String,
+        /// `None` for spectators.
+        player: Option<usize>,
+        updates: broadcast::Receiver<RoomState>,
+    }
+
+    // rooms only live as long as someone is connected to them
+    static ROOMS: OnceLock<Mutex<HashMap<String, Room>>> = OnceLock::new();
+
+    fn rooms() -> &'static Mutex<HashMap<String, Room>> {
+        ROOMS.get_or_init(Default::default)
+    }
+
+    pub async fn socket(upgrade: WebSocketUpgrade) -> Response {
+        upgrade.on_upgrade(serve)
+    }
+
+    async fn serve(mut socket: WebSocket) {
+        let mut seat = None::<Seat>;
+        loop {
+            tokio::select! {
+                message = socket.recv() => {
+                    let Some(Ok(message)) = message else {
+                        break;
+                    };
+                    let Message::Text(text) = message else {
+                        continue;
+                    };
+                    let reply = match serde_json::from_str(&text) {
+                        Ok(message) => handle(message, &mut seat).map_err(|err| err.to_string()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    let reply = match reply {
+                        Ok(Some(reply)) => reply,
+                        Ok(None) => continue,
+                        Err(message) => ServerMessage::Error { message },
+                    };
+                    if send(&mut socket, &reply).await.is_err() {
+                        break;
+                    }
+                }
+                room = next_update(&mut seat) => {
+                    let Some(room) = room else {
+                        break;
+                    };
+                    if send(&mut socket, &ServerMessage::Update { room }).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(Seat {
+            code,
+            player,
+            updates,
+        }) = seat
+        {
+            drop(updates);
+            let mut rooms = rooms().lock().unwrap();
+            let Some(room) = rooms.get_mut(&code) else {
+                return;
+            };
+            // the seat stays so nobody else's number changes, its turns are skipped from now on
+            if let Some(player) = player {
+                // the next player mustn't get to finish a selection begun by someone else
+                if room.state.turns.current() == player && room.board.unselect() {
+                    room.state.events.push(BoardEvent::Unselect);
+                }
+                room.state.turns.remove_player(player);
+                let _ = room.updates.send(room.state.clone());
+            }
+            if room.updates.receiver_count() == 0 {
+                rooms.remove(&code);
+            }
+        }
+    }
+
+    async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+        let json = serde_json::to_string(message).expect("messages always serialize");
+        socket.send(Message::Text(json)).await
+    }
+
+    // waits forever until seated, every update is a full snapshot so lagging behind is harmless
+    async fn next_update(seat: &mut Option<Seat>) -> Option<RoomState> {
+        let Some(seat) = seat else {
+            return std::future::pending().await;
+        };
+        loop {
+            match seat.updates.recv().await {
+                Ok(room) => return Some(room),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    fn handle(
+        message: ClientMessage,
+        seat: &mut Option<Seat>,
+    ) -> Result<Option<ServerMessage>, RoomError> {
+        let mut rooms = rooms().lock().unwrap();
+        let reply = match message {
+            ClientMessage::Create { name, size } => {
+                if seat.is_some() {
+                    return Err(RoomError::AlreadySeated);
+                }
+                let name = valid_name(&name)?;
+                if !SIZES.contains(&size) {
+                    return Err(RoomError::InvalidSize(size));
+                }
+
+                let code = loop {
+                    let code: String = (0..4).map(|_| fastrand::uppercase()).collect();
+                    if !rooms.contains_key(&code) {
+                        break code;
+                    }
+                };
+                let seed = fastrand::u64(..);
+                let state = RoomState {
+                    code: code.clone(),
+                    size,
+                    seed,
+                    players: vec![name],
+                    turns: Turns::new(1),
+                    events: Vec::new(),
+                };
+                let (updates, receiver) = broadcast::channel(16);
+                rooms.insert(
+                    code.clone(),
+                    Room {
+                        state: state.clone(),
+                        board: blank_board(size, seed),
+                        updates,
+                    },
+                );
+                *seat = Some(Seat {
+                    code,
+                    player: Some(0),
+                    updates: receiver,
+                });
+                Some(ServerMessage::Joined {
+                    player: 0,
+                    room: state,
+                })
+            }
+            ClientMessage::Join { name, code } => {
+                if seat.is_some() {
+                    return Err(RoomError::AlreadySeated);
+                }
+                let name = valid_name(&name)?;
+                let code = code.trim().to_uppercase();
+                let room = rooms
+                    .get_mut(&code)
+                    .ok_or_else(|| RoomError::NoSuchRoom(code.clone()))?;
+                if !room.state.events.is_empty() {
+                    return Err(RoomError::AlreadyStarted);
+                }
+                if room.state.players.len() >= MAX_PLAYERS {
+                    return Err(RoomError::Full);
+                }
+
+                let player = room.state.players.len();
+                room.state.players.push(name);
+                room.state.turns.add_player();
+                let _ = room.updates.send(room.state.clone());
+                *seat = Some(Seat {
+                    code,
+                    player: Some(player),
+                    updates: room.updates.subscribe(),
+                });
+                Some(ServerMessage::Joined {
+                    player,
+                    room: room.state.clone(),
+                })
+            }
+            ClientMessage::Watch { code } => {
+                if seat.is_some() {
+                    return Err(RoomError::AlreadySeated);
+                }
+                let code = code.trim().to_uppercase();
+                let room = rooms
+                    .get(&code)
+                    .ok_or_else(|| RoomError::NoSuchRoom(code.clone()))?;
+
+                *seat = Some(Seat {
+                    code,
+                    player: None,
+                    updates: room.updates.subscribe(),
+                });
+                Some(ServerMessage::Watching {
+                    room: room.state.clone(),
+                })
+            }
+            ClientMessage::Flip { card } => {
+                let seat = seat.as_ref().ok_or(RoomError::NotSeated)?;
+                let player = seat.player.ok_or(RoomError::Spectating)?;
+                let room = rooms
+                    .get_mut(&seat.code)
+                    .ok_or_else(|| RoomError::NoSuchRoom(seat.code.clone()))?;
+                if room.state.is_waiting() {
+                    return Err(RoomError::Waiting);
+                }
+                if room.state.is_abandoned() {
+                    return Err(RoomError::Abandoned);
+                }
+                if room.state.turns.current() != player {
+                    return Err(RoomError::NotYourTurn);
+                }
+
+                let outcome = room.board.flip(card);
+                if outcome == FlipOutcome::Ignored {
+                    return Ok(None);
+                }
+                room.state.turns.record(outcome);
+                room.state.events.push(BoardEvent::Flip { card });
+                let _ = room.updates.send(room.state.clone());
+                None
+            }
+        };
+        Ok(reply)
+    }
+
+    fn valid_name(name: &str) -> Result<String, RoomError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > 20 {
+            return Err(RoomError::InvalidName);
+        }
+        Ok(name.to_string())
+    }
+}
+
+pub use client::Socket;
+
+mod client {
+    use super::{ClientMessage, ServerMessage, PATH};
+    use leptos::window;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::{MessageEvent, WebSocket};
+
+    /// A connection to the room server, closed when dropped.
+    pub struct Socket {
+        socket: WebSocket,
+        _on_open: Closure<dyn FnMut()>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut()>,
+    }
+
+    impl Socket {
+        /// Connects and sends `hello` as soon as the socket is open, `on_message` sees everything
+        /// the server sends back and an error once the connection is gone.
+        pub fn connect(
+            hello: ClientMessage,
+            on_message: impl Fn(ServerMessage) + 'static,
+        ) -> Result<Self, JsValue> {
+            let location = window().location();
+            let scheme = if location.protocol()? == "https:" {
+                "wss"
+            } else {
+                "ws"
+            };
+            let socket = WebSocket::new(&format!("{scheme}://{}{PATH}", location.host()?))?;
+
+            let on_message = std::rc::Rc::new(on_message);
+            let opened = socket.clone();
+            let on_open = Closure::<dyn FnMut()>::new(move || send(&opened, &hello));
+            let received = on_message.clone();
+            let on_message_event = Closure::<dyn FnMut(_)>::new(move |event: MessageEvent| {
+                let message = event
+                    .data()
+                    .as_string()
+                    .and_then(|json| serde_json::from_str(&json).ok());
+                if let Some(message) = message {
+                    received(message);
+                }
+            });
+            let on_close = Closure::<dyn FnMut()>::new(move || {
+                on_message(ServerMessage::Error {
+                    message: "Lost the connection to the room".to_string(),
+                })
+            });
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message_event.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+            Ok(Self {
+                socket,
+                _on_open: on_open,
+                _on_message: on_message_event,
+                _on_close: on_close,
+            })
+        }
+
+        pub fn send(&self, message: &ClientMessage) {
+            send(&self.socket, message);
+        }
+    }
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            let _ = self.socket.close();
+        }
+    }
+
+    fn send(socket: &WebSocket, message: &ClientMessage) {
+        let json = serde_json::to_string(message).expect("messages always serialize");
+        if let Err(err) = socket.send_with_str(&json) {
+            log::error!("couldn't send to the room: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::{self, symbol_pairs, take_pairs};
+
+    // the server checks flips against this board while clients draw theirs with `deck::deal`
+    #[test]
+    fn blank_boards_match_the_dealt_ones() {
+        for size in [*SIZES.start(), 8, *SIZES.end()] {
+            let pairs = take_pairs(symbol_pairs(2), size).unwrap();
+            for seed in [0, 1, 20240309, u64::MAX] {
+                let blank = blank_board(size, seed);
+                let dealt = deck::deal(&pairs, seed);
+                assert_eq!(blank.len(), dealt.len());
+                for id in 0..dealt.len() {
+                    assert_eq!(blank.pair(id), dealt.pair(id), "{size} pairs, seed {seed}");
+                }
+            }
+        }
+    }
+}
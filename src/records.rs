@@ -0,0 +1,155 @@
+use crate::{
+    engine::{Rules, Stats},
+    storage,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+const STORAGE_KEY: &str = "personal-bests";
+
+/// Best time and fewest moves for one mode and size, not necessarily from the same game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersonalBest {
+    pub time: Duration,
+    pub moves: u32,
+}
+
+/// Which records a finished game broke.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NewRecords {
+    pub time: bool,
+    pub moves: bool,
+}
+
+impl NewRecords {
+    pub fn any(&self) -> bool {
+        self.time || self.moves
+    }
+}
+
+impl PersonalBest {
+    /// Merges a finished game into the best so far, a first game sets records without breaking any.
+    pub fn update(best: Option<Self>, stats: &Stats) -> (Self, NewRecords) {
+        let Some(best) = best else {
+            let best = Self {
+                time: stats.elapsed,
+                moves: stats.moves,
+            };
+            return (best, NewRecords::default());
+        };
+
+        let new_records = NewRecords {
+            time: stats.elapsed < best.time,
+            moves: stats.moves < best.moves,
+        };
+        let best = Self {
+            time: best.time.min(stats.elapsed),
+            moves: best.moves.min(stats.moves),
+        };
+        (best, new_records)
+    }
+}
+
+pub type PersonalBests = HashMap<String, PersonalBest>;
+
+/// What was played, records and leaderboards are kept per kind.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameKind {
+    /// e.g. `classic`, `triples` or `deck/animals`.
+    pub mode: String,
+    /// Number of pairs, or groups, on the board.
+    pub size: usize,
+}
+
+impl GameKind {
+    pub fn new(mode: impl Into<String>, size: usize) -> Self {
+        Self {
+            mode: mode.into(),
+            size,
+        }
+    }
+
+    /// e.g. `classic/8`.
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.mode, self.size)
+    }
+
+    pub fn rules(&self) -> Rules {
+        match self.mode.as_str() {
+            // a couple of seconds a pair to begin with, the rest has to be earned
+            "clock" => Rules::Countdown {
+                limit: Duration::from_secs(10 + 2 * self.size as u64),
+                bonus: Duration::from_secs(3),
+            },
+            // bigger boards leave more room for bad guesses
+            "survival" => Rules::Lives(2 + self.size as u32 / 2),
+            _ => Rules::Relaxed,
+        }
+    }
+}
+
+pub async fn load_personal_bests() -> PersonalBests {
+    storage::load_json(STORAGE_KEY).await.unwrap_or_default()
+}
+
+pub async fn submit(kind: &GameKind, stats: &Stats) -> NewRecords {
+    let key = kind.key();
+    let mut bests = load_personal_bests().await;
+    let previous = bests.get(&key).copied();
+    let (best, new_records) = PersonalBest::update(previous, stats);
+    if previous != Some(best) {
+        bests.insert(key, best);
+        storage::store_json(STORAGE_KEY, &bests).await;
+    }
+    new_records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(moves: u32, secs: u64) -> Stats {
+        Stats {
+            moves,
+            elapsed: Duration::from_secs(secs),
+            ..Stats::default()
+        }
+    }
+
+    fn best(moves: u32, secs: u64) -> PersonalBest {
+        PersonalBest {
+            time: Duration::from_secs(secs),
+            moves,
+        }
+    }
+
+    #[test]
+    fn a_first_game_breaks_no_records() {
+        assert_eq!(
+            PersonalBest::update(None, &stats(10, 60)),
+            (best(10, 60), NewRecords::default())
+        );
+    }
+
+    #[test]
+    fn ties_are_not_records() {
+        assert_eq!(
+            PersonalBest::update(Some(best(10, 60)), &stats(10, 60)),
+            (best(10, 60), NewRecords::default())
+        );
+    }
+
+    #[test]
+    fn bests_are_merged_from_different_games() {
+        let (merged, new_records) = PersonalBest::update(Some(best(10, 60)), &stats(12, 45));
+        assert_eq!(merged, best(10, 45));
+        assert_eq!(
+            new_records,
+            NewRecords {
+                time: true,
+                moves: false
+            }
+        );
+        assert!(new_records.any());
+    }
+}
@@ -0,0 +1,53 @@
+//! Recorded matches, saved as JSON so they can be watched again.
+
+use crate::{
+    daily,
+    deck::{classic_group_size, fetch_deck, symbol_pairs, take_pairs, Pairs},
+    engine::{Flip, ReplayError},
+    error_template::AppError,
+    records::GameKind,
+};
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to deal the same board again and play the match back on it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub kind: GameKind,
+    pub seed: u64,
+    pub flips: Vec<Flip>,
+}
+
+impl Replay {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("replays always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        serde_json::from_str(json).map_err(|err| ReplayError::Parse(err.to_string()))
+    }
+
+    /// Parses a replay and checks it really clears the board it was dealt.
+    pub async fn load(json: &str) -> Result<(Self, Pairs), AppError> {
+        let replay = Self::from_json(json)?;
+        let pairs = load_pairs(&replay.kind).await?;
+        crate::deck::deal(&pairs, replay.seed).replay(replay.kind.rules(), &replay.flips)?;
+        Ok((replay, pairs))
+    }
+}
+
+/// The pairs a game of `kind` was dealt from.
+pub async fn load_pairs(kind: &GameKind) -> Result<Pairs, AppError> {
+    if let Some(group_size) = classic_group_size(&kind.mode) {
+        return Ok(take_pairs(symbol_pairs(group_size), kind.size)?);
+    }
+    if *kind == daily::kind() {
+        return Ok(daily::pairs()?);
+    }
+
+    let id = kind.mode.strip_prefix("deck/").ok_or(AppError::NotFound)?;
+    let deck = fetch_deck(id).await?;
+    if deck.pairs.len() != kind.size {
+        return Err(AppError::NotFound);
+    }
+    Ok(deck.pairs)
+}
@@ -0,0 +1,184 @@
+//! Player preferences, kept in storage and shared with every page through context.
+
+use crate::{
+    audio::{Sound, DEFAULT_PACK},
+    storage,
+};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+const STORAGE_KEY: &str = "settings";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// From 0 to 1.
+    pub volume: f64,
+    pub muted: bool,
+    /// Each sound's share of `volume`, from 0 to 1, anything missing plays at full share.
+    pub sound_volumes: HashMap<Sound, f64>,
+    /// Folder of `public/sounds` the sounds come from.
+    pub sound_pack: String,
+    /// How long a finished selection stays revealed, `None` waits for the next flip.
+    pub settle_delay: Option<Duration>,
+    pub card_size: CardSize,
+    pub theme: Theme,
+    pub reduced_motion: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 0.2,
+            muted: false,
+            sound_volumes: HashMap::from([(Sound::Music, 0.5)]),
+            sound_pack: DEFAULT_PACK.to_string(),
+            settle_delay: Some(Duration::from_millis(1200)),
+            card_size: CardSize::default(),
+            theme: Theme::default(),
+            reduced_motion: false,
+        }
+    }
+}
+
+impl Settings {
+    /// What sounds should actually play at.
+    pub fn effective_volume(&self) -> f64 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn sound_volume(&self, sound: Sound) -> f64 {
+        self.sound_volumes
+            .get(&sound)
+            .copied()
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0)
+    }
+
+    /// Classes for `<body>`, the stylesheet handles the rest.
+    pub fn body_class(&self) -> String {
+        let mut class = format!("theme-{}", self.theme.id());
+        if self.reduced_motion {
+            class += " reduced-motion";
+        }
+        class
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardSize {
+    Small,
+    Medium,
+    /// As big as the screen allows.
+    #[default]
+    Large,
+}
+
+impl CardSize {
+    pub const ALL: [CardSize; 3] = [CardSize::Small, CardSize::Medium, CardSize::Large];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            CardSize::Small => "small",
+            CardSize::Medium => "medium",
+            CardSize::Large => "large",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CardSize::Small => "Small",
+            CardSize::Medium => "Medium",
+            CardSize::Large => "Large",
+        }
+    }
+
+    /// Widest a card gets, in pixels.
+    pub fn max_width(self) -> Option<f32> {
+        match self {
+            CardSize::Small => Some(120.0),
+            CardSize::Medium => Some(200.0),
+            CardSize::Large => None,
+        }
+    }
+
+    /// Space between cards, in pixels.
+    pub fn gap(self) -> usize {
+        match self {
+            CardSize::Small => 10,
+            CardSize::Medium | CardSize::Large => 20,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+    /// Blue and orange instead of green and red.
+    ColourBlind,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::Light,
+        Theme::Dark,
+        Theme::ColourBlind,
+        Theme::HighContrast,
+    ];
+
+    /// Matches a `body.theme-<id>` block of the stylesheet, which sets the colour variables.
+    pub fn id(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::ColourBlind => "colour-blind",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::ColourBlind => "Colour-blind friendly",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+}
+
+/// Starts with the defaults until the stored settings are loaded, then stores every change.
+pub fn provide_settings() -> RwSignal<Settings> {
+    let settings = create_rw_signal(Settings::default());
+    let (loaded, set_loaded) = create_signal(false);
+    create_effect(move |_| {
+        spawn_local(async move {
+            if let Some(stored) = storage::load_json(STORAGE_KEY).await {
+                settings.set(stored);
+            }
+            set_loaded(true);
+        });
+    });
+    create_effect(move |_| {
+        let settings = settings.get();
+        if loaded() {
+            spawn_local(async move { storage::store_json(STORAGE_KEY, &settings).await });
+        }
+    });
+
+    provide_context(settings);
+    settings
+}
+
+pub fn use_settings() -> RwSignal<Settings> {
+    expect_context()
+}
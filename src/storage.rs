@@ -0,0 +1,68 @@
+//! Key/value storage that survives reloads.
+//!
+//! Inside the Tauri app values go through the `load_item`/`store_item` commands,
+//! everywhere else they live in the browser's local storage.
+
+use js_sys::{Function, Object, Promise, Reflect};
+use leptos::window;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+pub async fn load(key: &str) -> Option<String> {
+    if let Some(invoke) = tauri_invoke() {
+        let args = Object::new();
+        Reflect::set(&args, &"key".into(), &key.into()).ok()?;
+        return call(&invoke, "load_item", &args).await.ok()?.as_string();
+    }
+
+    window().local_storage().ok()??.get_item(key).ok()?
+}
+
+pub async fn store(key: &str, value: &str) {
+    if let Some(invoke) = tauri_invoke() {
+        let args = Object::new();
+        let _ = Reflect::set(&args, &"key".into(), &key.into());
+        let _ = Reflect::set(&args, &"value".into(), &value.into());
+        if let Err(err) = call(&invoke, "store_item", &args).await {
+            log::error!("couldn't store {key}: {err:?}");
+        }
+        return;
+    }
+
+    if let Ok(Some(storage)) = window().local_storage() {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+pub async fn load_json<T: serde::de::DeserializeOwned>(key: &str) -> Option<T> {
+    serde_json::from_str(&load(key).await?).ok()
+}
+
+pub async fn store_json<T: serde::Serialize>(key: &str, value: &T) {
+    if let Ok(json) = serde_json::to_string(value) {
+        store(key, &json).await;
+    }
+}
+
+// `withGlobalTauri` exposes `invoke`, the module holding it was renamed between releases
+fn tauri_invoke() -> Option<Function> {
+    let tauri = Reflect::get(&window(), &"__TAURI__".into()).ok()?;
+    if tauri.is_undefined() {
+        return None;
+    }
+
+    ["core", "primitives", "tauri"]
+        .into_iter()
+        .find_map(|module| {
+            let module = Reflect::get(&tauri, &module.into()).ok()?;
+            Reflect::get(&module, &"invoke".into())
+                .ok()?
+                .dyn_into::<Function>()
+                .ok()
+        })
+}
+
+async fn call(invoke: &Function, command: &str, args: &Object) -> Result<JsValue, JsValue> {
+    let promise: Promise = invoke.call2(&JsValue::NULL, &command.into(), args)?.into();
+    JsFuture::from(promise).await
+}